@@ -0,0 +1,262 @@
+use crate::expr::Expr;
+use crate::{Matcher, PasswordPolicy, Rule, Target, PARTS};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+fn describe(target: &Target) -> String {
+    match target {
+        Target::Letter(letter) => format!("letter '{}'", letter),
+        Target::Class { .. } => format!("class {}", target),
+    }
+}
+
+// Why a password fails a count rule
+pub fn explain_count(policy: &PasswordPolicy, password: &str, matcher: Matcher) -> String {
+    let count = matcher
+        .letters(password)
+        .iter()
+        .filter(|letter| matcher.is_match(letter, &policy.target))
+        .count();
+    format!(
+        "{} appears {} times, allowed {}-{}",
+        describe(&policy.target),
+        count,
+        policy.low,
+        policy.high
+    )
+}
+
+// Why a password fails a positional rule
+pub fn explain_positions(policy: &PasswordPolicy, password: &str, matcher: Matcher) -> String {
+    let letters = matcher.letters(password);
+    for &pos in &[policy.low, policy.high] {
+        if pos == 0 || pos > letters.len() {
+            return format!(
+                "position {} is out of range for length {}",
+                pos,
+                letters.len()
+            );
+        }
+    }
+    let (low, high) = (policy.low, policy.high);
+    if matcher.is_match(letters[low - 1], &policy.target) {
+        format!("positions {} and {} both match", low, high)
+    } else {
+        format!("neither position {} nor {} matches", low, high)
+    }
+}
+
+// Reasons why `password` fails `expr`, empty if it passes
+pub fn explain(expr: &Expr, password: &str, matcher: Matcher, bare: Rule) -> Vec<String> {
+    match expr {
+        Expr::Clause(rule, policy) => {
+            let rule = rule.unwrap_or(bare);
+            if rule.is_satisfied(policy, password, matcher) {
+                vec![]
+            } else {
+                vec![rule.explain(policy, password, matcher)]
+            }
+        }
+        Expr::Regex(_) if !expr.evaluate(password, matcher, bare) => {
+            vec![format!("does not match {}", expr)]
+        }
+        Expr::Not(inner) if !expr.evaluate(password, matcher, bare) => {
+            vec![format!("must not satisfy {}", inner)]
+        }
+        Expr::And(lhs, rhs) => {
+            let mut reasons = explain(lhs, password, matcher, bare);
+            reasons.extend(explain(rhs, password, matcher, bare));
+            reasons
+        }
+        Expr::Or(lhs, rhs) if !expr.evaluate(password, matcher, bare) => {
+            let mut reasons = explain(lhs, password, matcher, bare);
+            reasons.extend(explain(rhs, password, matcher, bare));
+            reasons
+        }
+        _ => vec![],
+    }
+}
+
+#[derive(Default, PartialEq, Debug)]
+pub struct Counts {
+    pub passwords: usize,
+    pub valid_part1: usize,
+    pub valid_part2: usize,
+}
+
+// Password counts per (letter, range) of each clause. A line with several
+// clauses counts towards each of them; regex-only lines are not counted.
+pub struct Summary {
+    pub rows: BTreeMap<(String, String), Counts>,
+}
+impl Summary {
+    pub fn new(pairs: &[(Expr, String)], matcher: Matcher) -> Summary {
+        let mut rows: BTreeMap<(String, String), Counts> = BTreeMap::new();
+
+        for (expr, password) in pairs {
+            let [valid_part1, valid_part2] =
+                PARTS.map(|(_, bare)| expr.evaluate(password, matcher, bare));
+
+            for policy in expr.clauses() {
+                let range = format!("{}-{}", policy.low, policy.high);
+                let counts = rows.entry((policy.target.to_string(), range)).or_default();
+                counts.passwords += 1;
+                counts.valid_part1 += valid_part1 as usize;
+                counts.valid_part2 += valid_part2 as usize;
+            }
+        }
+        Summary { rows }
+    }
+
+    pub fn export(&self, format: Format) -> String {
+        let mut out = String::new();
+        match format {
+            Format::Csv => {
+                out.push_str("letter,range,passwords,valid_part1,valid_part2\n");
+                for ((letter, range), counts) in &self.rows {
+                    out.push_str(&format!(
+                        "{},{},{},{},{}\n",
+                        csv_field(letter),
+                        range,
+                        counts.passwords,
+                        counts.valid_part1,
+                        counts.valid_part2
+                    ));
+                }
+            }
+            Format::Json => {
+                out.push('[');
+                for (i, ((letter, range), counts)) in self.rows.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&format!(
+                        "\n  {{\"letter\": {}, \"range\": \"{}\", \"passwords\": {}, \"valid_part1\": {}, \"valid_part2\": {}}}",
+                        json_string(letter),
+                        range,
+                        counts.passwords,
+                        counts.valid_part1,
+                        counts.valid_part2
+                    ));
+                }
+                out.push_str("\n]\n");
+            }
+        }
+        out
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_policy_and_password, CountRange, PositionalXor};
+
+    const COUNT: Rule = Rule(&CountRange);
+    const POS: Rule = Rule(&PositionalXor);
+
+    fn reasons(line: &str, bare: Rule) -> Vec<String> {
+        let (expr, password) = parse_policy_and_password(line.into());
+        explain(&expr, &password, Matcher::default(), bare)
+    }
+
+    #[test]
+    fn count_explanations() {
+        assert_eq!(
+            reasons("2-8 h: hhhhhhhhh", COUNT),
+            vec!["letter 'h' appears 9 times, allowed 2-8"]
+        );
+        assert_eq!(
+            reasons("1-2 [0-9]: abc", COUNT),
+            vec!["class [0-9] appears 0 times, allowed 1-2"]
+        );
+        assert!(reasons("1-3 a: abcde", COUNT).is_empty());
+    }
+
+    #[test]
+    fn position_explanations() {
+        assert_eq!(
+            reasons("1-3 a: aba", POS),
+            vec!["positions 1 and 3 both match"]
+        );
+        assert_eq!(
+            reasons("1-3 b: cdefg", POS),
+            vec!["neither position 1 nor 3 matches"]
+        );
+        assert_eq!(
+            reasons("1-9 a: abc", POS),
+            vec!["position 9 is out of range for length 3"]
+        );
+        assert!(reasons("1-3 a: abcde", POS).is_empty());
+    }
+
+    #[test]
+    fn compound_explanations() {
+        assert_eq!(
+            reasons("count 1-1 a AND NOT /c/ AND (/x/ OR 2-2 b): aabc", COUNT),
+            vec![
+                "letter 'a' appears 2 times, allowed 1-1",
+                "must not satisfy /c/",
+                "does not match /x/",
+                "letter 'b' appears 1 times, allowed 2-2",
+            ]
+        );
+        assert!(reasons("1-1 z OR 1-1 a: abc", COUNT).is_empty());
+    }
+
+    #[test]
+    fn summary_export() {
+        let pairs: Vec<(Expr, String)> = ["1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc"]
+            .iter()
+            .map(|line| parse_policy_and_password(line.to_string()))
+            .collect();
+        let summary = Summary::new(&pairs, Matcher::default());
+
+        assert_eq!(
+            summary.export(Format::Csv),
+            "letter,range,passwords,valid_part1,valid_part2\n\
+             a,1-3,1,1,1\n\
+             b,1-3,1,0,0\n\
+             c,2-9,1,1,0\n"
+        );
+        assert_eq!(
+            summary.export(Format::Json).lines().nth(1),
+            Some(
+                r#"  {"letter": "a", "range": "1-3", "passwords": 1, "valid_part1": 1, "valid_part2": 1},"#
+            )
+        );
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(csv_field("[,]"), "\"[,]\"");
+        assert_eq!(json_string("\"\\"), r#""\"\\""#);
+    }
+}
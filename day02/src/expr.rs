@@ -0,0 +1,389 @@
+// Policy expressions, e.g.
+//
+//     count 2-8 h AND (pos 1-3 [0-9] OR NOT /^admin/): password
+//
+// A clause is a policy with an optional rule keyword (see `RULES`, e.g. `count`
+// or `pos`) or a regex between slashes. Clauses combine with NOT, AND and OR, in decreasing order
+// of precedence, and parentheses. The one-line puzzle format "2-8 h: password"
+// is a single bare clause whose rule is decided when it is evaluated.
+//
+// The letters `(`, `)`, `:` and `/` can only be targeted inside a class, e.g. `[:]`,
+// or by a line in the one-line format, e.g. "1-3 :: password".
+
+use crate::{Matcher, PasswordPolicy, Rule};
+use regex::{Regex, RegexBuilder};
+use std::fmt;
+use unicode_segmentation::UnicodeSegmentation;
+
+// A regex compiled both ways, so it folds case like the other clauses when
+// the matcher does
+#[derive(Debug)]
+pub struct Pattern {
+    case_sensitive: Regex,
+    ignore_case: Regex,
+}
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Pattern, String> {
+        let build = |ignore_case| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                .build()
+                .map_err(|e| e.to_string())
+        };
+        Ok(Pattern {
+            case_sensitive: build(false)?,
+            ignore_case: build(true)?,
+        })
+    }
+    fn is_match(&self, password: &str, matcher: Matcher) -> bool {
+        if matcher.case_sensitive {
+            self.case_sensitive.is_match(password)
+        } else {
+            self.ignore_case.is_match(password)
+        }
+    }
+    fn as_str(&self) -> &str {
+        self.case_sensitive.as_str()
+    }
+}
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Expr {
+    Clause(Option<Rule>, PasswordPolicy),
+    Regex(Pattern),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+impl Expr {
+    // `bare` is the rule used for clauses written without a keyword
+    pub fn evaluate(&self, password: &str, matcher: Matcher, bare: Rule) -> bool {
+        match self {
+            Expr::Clause(rule, policy) => {
+                rule.unwrap_or(bare).is_satisfied(policy, password, matcher)
+            }
+            Expr::Regex(pattern) => pattern.is_match(password, matcher),
+            Expr::Not(expr) => !expr.evaluate(password, matcher, bare),
+            Expr::And(lhs, rhs) => {
+                lhs.evaluate(password, matcher, bare) && rhs.evaluate(password, matcher, bare)
+            }
+            Expr::Or(lhs, rhs) => {
+                lhs.evaluate(password, matcher, bare) || rhs.evaluate(password, matcher, bare)
+            }
+        }
+    }
+}
+
+impl Expr {
+    pub fn clauses(&self) -> Vec<&PasswordPolicy> {
+        match self {
+            Expr::Clause(_, policy) => vec![policy],
+            Expr::Regex(_) => vec![],
+            Expr::Not(expr) => expr.clauses(),
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                let mut clauses = lhs.clauses();
+                clauses.extend(rhs.clauses());
+                clauses
+            }
+        }
+    }
+    fn is_compound(&self) -> bool {
+        matches!(self, Expr::And(..) | Expr::Or(..))
+    }
+}
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Clause(rule, policy) => {
+                if let Some(rule) = rule {
+                    write!(f, "{} ", rule.keyword())?;
+                }
+                write!(f, "{}-{} {}", policy.low, policy.high, policy.target)
+            }
+            Expr::Regex(pattern) => write!(f, "/{}/", pattern.as_str().replace('/', "\\/")),
+            Expr::Not(expr) if expr.is_compound() => write!(f, "NOT ({})", expr),
+            Expr::Not(expr) => write!(f, "NOT {}", expr),
+            Expr::And(lhs, rhs) => {
+                for (i, expr) in [lhs, rhs].iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " AND ")?;
+                    }
+                    if matches!(***expr, Expr::Or(..)) {
+                        write!(f, "({})", expr)?;
+                    } else {
+                        write!(f, "{}", expr)?;
+                    }
+                }
+                Ok(())
+            }
+            Expr::Or(lhs, rhs) => write!(f, "{} OR {}", lhs, rhs),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+enum Token {
+    LParen,
+    RParen,
+    Word(String),
+    Regex(String),
+}
+
+// Splits "<expression>: <password>" into the expression's tokens and the password
+fn tokenize(line: &str) -> Result<(Vec<Token>, &str), String> {
+    let mut tokens = vec![];
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            ':' => return Ok((tokens, line[i + 1..].trim())),
+            '/' => {
+                let mut pattern = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '/')) => break,
+                        Some((_, '\\')) if matches!(chars.peek(), Some((_, '/'))) => {
+                            pattern.push('/');
+                            chars.next();
+                        }
+                        Some((_, c)) => pattern.push(c),
+                        None => return Err(format!("unterminated regex: /{}", pattern)),
+                    }
+                }
+                tokens.push(Token::Regex(pattern));
+            }
+            _ => {
+                let mut end = i + c.len_utf8();
+                let in_class = c == '[';
+                while let Some(&(j, c)) = chars.peek() {
+                    let is_boundary = if in_class {
+                        line[i..j].ends_with(']') && j - i > 2
+                    } else {
+                        c.is_whitespace() || "():/".contains(c)
+                    };
+                    if is_boundary {
+                        break;
+                    }
+                    end = j + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token::Word(line[i..end].to_string()));
+            }
+        }
+    }
+    Err("missing ':' before password".to_string())
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+impl Parser {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+    fn next(&mut self) -> Option<&Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1)
+    }
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.peek_keyword("OR") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while self.peek_keyword("AND") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek_keyword("NOT") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        let rule = match self.tokens.get(self.pos) {
+            Some(Token::Word(word)) => Rule::lookup(word),
+            _ => None,
+        };
+        if rule.is_some() {
+            self.pos += 1;
+        }
+
+        match self.next() {
+            Some(Token::LParen) if rule.is_none() => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err("missing ')'".to_string()),
+                }
+            }
+            Some(Token::Regex(pattern)) if rule.is_none() => {
+                Ok(Expr::Regex(Pattern::new(pattern)?))
+            }
+            Some(Token::Word(range)) => {
+                let range = range.clone();
+                let target = match self.next() {
+                    Some(Token::Word(target)) => target,
+                    _ => return Err(format!("missing letter after {}", range)),
+                };
+                let policy = format!("{} {}", range, target).parse()?;
+                Ok(Expr::Clause(rule, policy))
+            }
+            token => Err(format!("unexpected {:?}", token)),
+        }
+    }
+}
+
+// Falls back to the one-line format for letters the grammar cannot target
+// outside a class, e.g. "1-3 (: abc", keeping the expression's error if both fail
+pub fn parse(line: &str) -> Result<(Expr, &str), String> {
+    parse_expr(line).or_else(|err| parse_plain(line).map_err(|_| err))
+}
+
+// "min-max c: password", with any single letter as c
+fn parse_plain(line: &str) -> Result<(Expr, &str), String> {
+    let (range, rest) = line.trim_start().split_once(' ').ok_or("missing letter")?;
+    let rest = rest.trim_start();
+    let letter = rest.graphemes(true).next().ok_or("missing letter")?;
+    let password = rest[letter.len()..]
+        .strip_prefix(':')
+        .ok_or("missing ':' before password")?;
+
+    let policy = format!("{} {}", range, letter).parse()?;
+    Ok((Expr::Clause(None, policy), password.trim()))
+}
+
+fn parse_expr(line: &str) -> Result<(Expr, &str), String> {
+    let (tokens, password) = tokenize(line)?;
+    let mut parser = Parser { tokens, pos: 0 };
+
+    let expr = parser.or()?;
+    if let Some(token) = parser.next() {
+        return Err(format!("unexpected {:?}", token));
+    }
+    Ok((expr, password))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CountRange, PositionalXor, Target};
+
+    const COUNT: Rule = Rule(&CountRange);
+    const POS: Rule = Rule(&PositionalXor);
+
+    fn check(line: &str, bare: Rule) -> bool {
+        let (expr, password) = parse(line).unwrap();
+        expr.evaluate(password, Matcher::default(), bare)
+    }
+
+    #[test]
+    fn old_format() {
+        assert!(check("1-3 a: abcde", COUNT));
+        assert!(check("1-3 a: abcde", POS));
+        assert!(check("2-9 c: ccccccccc", COUNT));
+        assert!(!check("2-9 c: ccccccccc", POS));
+
+        // Letters the grammar treats as delimiters
+        assert!(check("1-3 :: :bc", COUNT));
+        assert!(check("1-3 (: (bc", POS));
+        assert!(check("2-2 /: a//", COUNT));
+        let (expr, password) = parse("1-3 ): abc").unwrap();
+        assert_eq!(expr.to_string(), "1-3 )");
+        assert_eq!(password, "abc");
+    }
+
+    #[test]
+    fn parse_tree() {
+        let (expr, password) = parse("count 1-3 a AND NOT (pos 1-2 [ab] OR /x+/): abc").unwrap();
+        let policy = |low, high, target: &str| PasswordPolicy {
+            low,
+            high,
+            target: target.parse::<Target>().unwrap(),
+        };
+        assert_eq!(
+            expr,
+            Expr::And(
+                Box::new(Expr::Clause(Some(COUNT), policy(1, 3, "a"))),
+                Box::new(Expr::Not(Box::new(Expr::Or(
+                    Box::new(Expr::Clause(Some(POS), policy(1, 2, "[ab]"))),
+                    Box::new(Expr::Regex(Pattern::new("x+").unwrap()))
+                ))))
+            )
+        );
+        assert_eq!(password, "abc");
+    }
+
+    #[test]
+    fn precedence() {
+        // AND binds tighter than OR, NOT tighter than AND
+        assert!(check(
+            "count 1-1 z OR count 1-1 a AND count 1-1 b: ab",
+            COUNT
+        ));
+        assert!(!check(
+            "(count 1-1 z OR count 1-1 a) AND count 1-1 c: ab",
+            COUNT
+        ));
+        assert!(check("NOT count 1-1 z AND count 1-1 a: ab", COUNT));
+    }
+
+    #[test]
+    fn mixed_clauses() {
+        let rule = "count 2-8 [a-z] AND pos 1-2 [0-9] AND /[A-Z]/";
+        assert!(check(&format!("{}: 1abcD", rule), COUNT));
+        assert!(!check(&format!("{}: 12abcD", rule), COUNT));
+        assert!(!check(&format!("{}: 1abcd", rule), COUNT));
+        assert!(check(r"/a\/b/ or count 3-3 [:]: x:::", COUNT));
+        assert!(check(r"/a\/b/ or count 3-3 [:]: a/b", COUNT));
+    }
+
+    #[test]
+    fn ignore_case() {
+        let ignore_case = Matcher {
+            case_sensitive: false,
+            ..Matcher::default()
+        };
+        let (expr, password) = parse("/^ADMIN/ AND count 1-1 x: adminX").unwrap();
+        assert!(!expr.evaluate(password, Matcher::default(), COUNT));
+        assert!(expr.evaluate(password, ignore_case, COUNT));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for line in &[
+            "2-8 h",
+            "count 1-3 [^a-z] AND NOT (pos 1-2 [ab] OR /x\\/+/)",
+            "(2-8 h OR 1-1 a) AND 3-4 b",
+            "2-8 h OR 1-1 a AND NOT 3-4 b",
+        ] {
+            let (expr, _) = parse(&format!("{}: pw", line)).unwrap();
+            assert_eq!(&expr.to_string(), line);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("1-3 a abcde").is_err());
+        assert!(parse("(1-3 a: abcde").is_err());
+        assert!(parse("1-3 a AND: abcde").is_err());
+        assert!(parse("1-3 a 1-2 b: abcde").is_err());
+        assert!(parse("count /a/: abcde").is_err());
+        assert!(parse("/(/: abcde").is_err());
+        assert!(parse("/abc: abcde").is_err());
+    }
+}
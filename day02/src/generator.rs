@@ -0,0 +1,363 @@
+// Sample passwords that pass or fail a policy, for testing other systems.
+// The same seed always gives the same passwords.
+
+use crate::{Matcher, PasswordPolicy, Rule, Target};
+
+// xorshift64*
+pub struct Rng(u64);
+impl Rng {
+    // The seed goes through splitmix64, so every seed gives its own state
+    pub fn new(seed: u64) -> Rng {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // The state must never be zero
+        Rng(if z == 0 { 0x9e37_79b9_7f4a_7c15 } else { z })
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+pub struct Generator {
+    rng: Rng,
+    matcher: Matcher,
+}
+impl Generator {
+    pub fn new(seed: u64, matcher: Matcher) -> Generator {
+        Generator {
+            rng: Rng::new(seed),
+            matcher,
+        }
+    }
+
+    fn is_valid(&self, policy: &PasswordPolicy, rule: Rule, password: &str) -> bool {
+        rule.is_satisfied(policy, password, self.matcher)
+    }
+
+    // Letters that do and do not match the target
+    fn pools(&self, target: &Target) -> (Vec<String>, Vec<String>) {
+        let mut candidates: Vec<String> = ('a'..='z')
+            .chain('A'..='Z')
+            .chain('0'..='9')
+            .map(String::from)
+            .collect();
+        match target {
+            Target::Letter(letter) => candidates.push(letter.clone()),
+            Target::Class { ranges, .. } => {
+                for &(lo, hi) in ranges {
+                    candidates.push(lo.to_string());
+                    candidates.push(hi.to_string());
+                }
+            }
+        }
+        candidates.sort();
+        candidates.dedup();
+
+        candidates
+            .into_iter()
+            .partition(|letter| self.matcher.is_match(letter, target))
+    }
+
+    fn letters(&self, password: &str) -> Vec<String> {
+        self.matcher
+            .letters(password)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    // A password that satisfies the policy, or None if no password can
+    pub fn valid(&mut self, policy: &PasswordPolicy, rule: Rule) -> Option<String> {
+        rule.generate(policy, self)
+    }
+
+    // A password that fails the policy, or None if every password passes
+    pub fn invalid(&mut self, policy: &PasswordPolicy, rule: Rule) -> Option<String> {
+        let password = self.valid(policy, rule).unwrap_or_default();
+        if !self.is_valid(policy, rule, &password) {
+            return Some(password);
+        }
+        self.mutate(policy, rule, &password)
+    }
+
+    // The password with the fewest letters changed, added or removed so that
+    // it flips between valid and invalid, or None if it cannot flip
+    pub fn mutate(
+        &mut self,
+        policy: &PasswordPolicy,
+        rule: Rule,
+        password: &str,
+    ) -> Option<String> {
+        rule.mutate(policy, password, self)
+    }
+
+    pub fn valid_count(&mut self, policy: &PasswordPolicy) -> Option<String> {
+        let (matching, other) = self.pools(&policy.target);
+        if matching.is_empty() || policy.low > policy.high {
+            return None;
+        }
+
+        let count = policy.low + self.rng.below(policy.high - policy.low + 1);
+        let extra = if other.is_empty() {
+            0
+        } else {
+            self.rng.below(6)
+        };
+
+        let mut letters = vec![];
+        for _ in 0..count {
+            letters.push(self.rng.pick(&matching).clone());
+        }
+        for _ in 0..extra {
+            letters.push(self.rng.pick(&other).clone());
+        }
+        self.rng.shuffle(&mut letters);
+        Some(letters.concat())
+    }
+
+    pub fn valid_positions(&mut self, policy: &PasswordPolicy) -> Option<String> {
+        let (matching, other) = self.pools(&policy.target);
+        if matching.is_empty() || other.is_empty() {
+            return None;
+        }
+        if policy.low == 0 || policy.high == 0 || policy.low == policy.high {
+            return None;
+        }
+
+        let len = policy.low.max(policy.high) + self.rng.below(4);
+        let mut letters = vec![];
+        for _ in 0..len {
+            letters.push(self.rng.pick(&other).clone());
+        }
+        let pos = *self.rng.pick(&[policy.low, policy.high]);
+        letters[pos - 1] = self.rng.pick(&matching).clone();
+        Some(letters.concat())
+    }
+
+    pub fn mutate_count(&mut self, policy: &PasswordPolicy, password: &str) -> Option<String> {
+        let (matching, other) = self.pools(&policy.target);
+        let mut letters = self.letters(password);
+        let matcher = self.matcher;
+        let is_match = |letter: &str| matcher.is_match(letter, &policy.target);
+
+        let count = letters.iter().filter(|letter| is_match(letter)).count();
+        let (low, high) = (policy.low, policy.high);
+        // No count satisfies the policy, so nothing can flip
+        if low > high {
+            return None;
+        }
+
+        // How many matches to add or remove
+        let (add, remove) = if policy.validate_qn1(password, matcher) {
+            let add = high + 1 - count;
+            let remove = if low > 0 && !other.is_empty() {
+                count + 1 - low
+            } else {
+                usize::MAX
+            };
+            if add <= remove {
+                (add, 0)
+            } else {
+                (0, remove)
+            }
+        } else if count < low {
+            (low - count, 0)
+        } else if count > high {
+            (0, count - high)
+        } else {
+            return None;
+        };
+        if add > 0 && matching.is_empty() {
+            return None;
+        }
+
+        let mut positions: Vec<usize> = (0..letters.len())
+            .filter(|&i| is_match(&letters[i]) == (remove > 0))
+            .collect();
+        self.rng.shuffle(&mut positions);
+
+        for &i in positions.iter().take(add.max(remove)) {
+            letters[i] = if add > 0 {
+                self.rng.pick(&matching).clone()
+            } else if other.is_empty() {
+                String::new()
+            } else {
+                self.rng.pick(&other).clone()
+            };
+        }
+        for _ in positions.len()..add {
+            let i = self.rng.below(letters.len() + 1);
+            letters.insert(i, self.rng.pick(&matching).clone());
+        }
+        Some(letters.concat())
+    }
+
+    pub fn mutate_positions(&mut self, policy: &PasswordPolicy, password: &str) -> Option<String> {
+        let (matching, other) = self.pools(&policy.target);
+        let mut letters = self.letters(password);
+        let matcher = self.matcher;
+        let is_match = |letter: &str| matcher.is_match(letter, &policy.target);
+
+        let (low, high) = (policy.low, policy.high);
+        if low == 0 || high == 0 || low == high {
+            return None;
+        }
+
+        if !policy.validate_qn2(password, matcher) {
+            if other.is_empty() {
+                return None;
+            }
+            while letters.len() < low.max(high) {
+                letters.push(self.rng.pick(&other).clone());
+            }
+            if policy.validate_qn2(&letters.concat(), matcher) {
+                return Some(letters.concat());
+            }
+        }
+
+        // Both or neither match when invalid, exactly one when valid,
+        // so changing either position flips the result
+        let pos = *self.rng.pick(&[low, high]);
+        let pool = if is_match(&letters[pos - 1]) {
+            &other
+        } else {
+            &matching
+        };
+        if pool.is_empty() {
+            return None;
+        }
+        letters[pos - 1] = self.rng.pick(pool).clone();
+        Some(letters.concat())
+    }
+
+    // `n` passwords alternating between valid and invalid, labelled with
+    // whether they should pass
+    pub fn samples(
+        &mut self,
+        policy: &PasswordPolicy,
+        rule: Rule,
+        n: usize,
+    ) -> Vec<(String, bool)> {
+        let mut samples = vec![];
+        for i in 0..n {
+            let sample = if i % 2 == 0 {
+                self.valid(policy, rule).map(|password| (password, true))
+            } else {
+                self.invalid(policy, rule).map(|password| (password, false))
+            };
+            samples.extend(sample);
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CountRange, PositionalXor};
+
+    const COUNT: Rule = Rule(&CountRange);
+    const POS: Rule = Rule(&PositionalXor);
+
+    fn policy(s: &str) -> PasswordPolicy {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn same_seed_same_passwords() {
+        let p = policy("2-8 h");
+        let a = Generator::new(7, Matcher::default()).samples(&p, COUNT, 10);
+        let b = Generator::new(7, Matcher::default()).samples(&p, COUNT, 10);
+        let c = Generator::new(8, Matcher::default()).samples(&p, COUNT, 10);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        // Neighbouring seeds must not share a state
+        let zero = Generator::new(0, Matcher::default()).samples(&p, COUNT, 10);
+        let one = Generator::new(1, Matcher::default()).samples(&p, COUNT, 10);
+        assert_ne!(zero, one);
+        for seed in 0..100 {
+            assert_ne!(Rng::new(seed).next_u64(), Rng::new(seed + 1).next_u64());
+        }
+    }
+
+    #[test]
+    fn minimal_mutations() {
+        let mut gen = Generator::new(1, Matcher::default());
+        let p = policy("1-3 a");
+
+        let flipped = gen.mutate(&p, COUNT, "xaaax").unwrap();
+        assert_eq!(flipped.len(), 5);
+        assert_eq!(flipped.matches('a').count(), 4);
+
+        let flipped = gen.mutate(&p, COUNT, "aaaaa").unwrap();
+        assert_eq!(flipped.matches('a').count(), 3);
+
+        let flipped = gen.mutate(&p, POS, "abcde").unwrap();
+        assert!(!p.validate_qn2(&flipped, Matcher::default()));
+        let changed = flipped.chars().zip("abcde".chars()).filter(|(a, b)| a != b);
+        assert_eq!(changed.count(), 1);
+
+        let flipped = gen.mutate(&p, POS, "a").unwrap();
+        assert!(p.validate_qn2(&flipped, Matcher::default()));
+    }
+
+    #[test]
+    fn impossible_policies() {
+        let mut gen = Generator::new(1, Matcher::default());
+        assert_eq!(gen.valid(&policy("3-1 a"), COUNT), None);
+        assert_eq!(gen.mutate(&policy("3-1 a"), COUNT, ""), None);
+        assert_eq!(gen.mutate(&policy("3-1 a"), COUNT, "aa"), None);
+        assert_eq!(gen.invalid(&policy("3-1 a"), COUNT), Some("".into()));
+        assert_eq!(gen.valid(&policy("2-2 a"), POS), None);
+        assert_eq!(gen.invalid(&policy("2-2 a"), POS), Some("".into()));
+    }
+
+    // The generator as an oracle for the validators
+    #[test]
+    fn validators_agree_with_generator() {
+        let mut rng = Rng::new(42);
+        let targets = ["a", "z", "é", "[0-9]", "[^a-z]", "[xyz]"];
+
+        for seed in 0..200 {
+            let low = 1 + rng.below(5);
+            let high = low + rng.below(5);
+            let target = targets[rng.below(targets.len())];
+            let p = policy(&format!("{}-{} {}", low, high, target));
+            let mut gen = Generator::new(seed, Matcher::default());
+
+            for &rule in &[COUNT, POS] {
+                for (password, expected) in gen.samples(&p, rule, 6) {
+                    let valid = if rule == COUNT {
+                        p.validate_qn1(&password, Matcher::default())
+                    } else {
+                        p.validate_qn2(&password, Matcher::default())
+                    };
+                    assert_eq!(valid, expected, "{:?} {:?} {}", p, rule, password);
+
+                    match gen.mutate(&p, rule, &password) {
+                        Some(flipped) => assert_eq!(gen.is_valid(&p, rule, &flipped), !expected),
+                        // Positional policies can never pass when both positions are the same
+                        None => assert!(rule == POS && low == high),
+                    }
+                }
+            }
+        }
+    }
+}
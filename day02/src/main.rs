@@ -1,68 +1,443 @@
+use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::str::Split;
+use std::ops::Deref;
+use std::str::FromStr;
+use unicode_segmentation::UnicodeSegmentation;
+
+mod audit;
+mod expr;
+mod generator;
+
+use audit::Format;
+use expr::Expr;
+use generator::Generator;
 
 #[derive(PartialEq, Debug)]
 struct PasswordPolicy {
-    rule: String,
-    pattern: String,
+    low: usize,
+    high: usize,
+    target: Target,
 }
 impl PasswordPolicy {
-    fn validate_qn1(&self, password: &str) -> bool {
-        let mut rule_splits = self.rule.split('-');
-        let (min, max) = get_next_two(&mut rule_splits);
-        let count = password.matches(&self.pattern).count();
+    fn validate_qn1(&self, password: &str, matcher: Matcher) -> bool {
+        CountRange.is_satisfied(self, password, matcher)
+    }
+    fn validate_qn2(&self, password: &str, matcher: Matcher) -> bool {
+        PositionalXor.is_satisfied(self, password, matcher)
+    }
+}
+impl FromStr for PasswordPolicy {
+    type Err = String;
+
+    // e.g. "2-8 h"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let range = parts.next().ok_or("missing range")?;
+        let target = parts.next().ok_or("missing letter")?;
+
+        let (low, high) = range
+            .split_once('-')
+            .ok_or_else(|| format!("bad range: {}", range))?;
+        let low = low.parse().map_err(|_| format!("bad number: {}", low))?;
+        let high = high.parse().map_err(|_| format!("bad number: {}", high))?;
+
+        if parts.next().is_some() {
+            return Err(format!("unexpected input after policy: {}", s));
+        }
+
+        Ok(PasswordPolicy {
+            low,
+            high,
+            target: target.parse()?,
+        })
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+enum Target {
+    // A single grapheme cluster, e.g. "h" or "é"
+    Letter(String),
+    // e.g. "[a-z0-9]" or "[^aeiou]", matched against a letter's base character
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+impl Target {
+    fn is_match(&self, letter: &str, case_sensitive: bool) -> bool {
+        match self {
+            Target::Letter(target) if case_sensitive => letter == target,
+            Target::Letter(target) => letter.to_lowercase() == target.to_lowercase(),
+            Target::Class { negated, ranges } => {
+                let c = match letter.chars().next() {
+                    Some(c) => c,
+                    None => return false,
+                };
+                let in_class = |c: char| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+                let found = if case_sensitive {
+                    in_class(c)
+                } else {
+                    in_class(c) || c.to_lowercase().chain(c.to_uppercase()).any(in_class)
+                };
+                found != *negated
+            }
+        }
+    }
+}
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Letter(letter) => write!(f, "{}", letter),
+            Target::Class { negated, ranges } => {
+                write!(f, "[{}", if *negated { "^" } else { "" })?;
+                for &(lo, hi) in ranges {
+                    if lo == hi {
+                        write!(f, "{}", lo)?;
+                    } else {
+                        write!(f, "{}-{}", lo, hi)?;
+                    }
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() > 2 && s.starts_with('[') && s.ends_with(']') {
+            let inner = &s[1..s.len() - 1];
+            let (negated, inner) = match inner.strip_prefix('^') {
+                Some(rest) => (true, rest),
+                None => (false, inner),
+            };
+
+            let chars: Vec<char> = inner.chars().collect();
+            let mut ranges = vec![];
+            let mut i = 0;
+            while i < chars.len() {
+                if i + 2 < chars.len() && chars[i + 1] == '-' {
+                    if chars[i] > chars[i + 2] {
+                        return Err(format!("bad class range: {}", s));
+                    }
+                    ranges.push((chars[i], chars[i + 2]));
+                    i += 3;
+                } else {
+                    ranges.push((chars[i], chars[i]));
+                    i += 1;
+                }
+            }
+            if ranges.is_empty() {
+                return Err(format!("empty class: {}", s));
+            }
+
+            return Ok(Target::Class { negated, ranges });
+        }
+
+        if s.graphemes(true).count() != 1 {
+            return Err(format!("expected a single letter: {}", s));
+        }
+        Ok(Target::Letter(s.to_string()))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Segmentation {
+    Chars,
+    Graphemes,
+}
+
+// How a password is split into letters and how letters are compared
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Matcher {
+    segmentation: Segmentation,
+    case_sensitive: bool,
+}
+impl Matcher {
+    fn letters<'a>(&self, password: &'a str) -> Vec<&'a str> {
+        match self.segmentation {
+            Segmentation::Chars => password
+                .char_indices()
+                .map(|(i, c)| &password[i..i + c.len_utf8()])
+                .collect(),
+            Segmentation::Graphemes => password.graphemes(true).collect(),
+        }
+    }
+    fn is_match(&self, letter: &str, target: &Target) -> bool {
+        target.is_match(letter, self.case_sensitive)
+    }
+    // A letter of several scalars, e.g. "e\u{301}", never matches a single one
+    fn check(&self, target: &Target) -> Result<(), String> {
+        match target {
+            Target::Letter(letter)
+                if self.segmentation == Segmentation::Chars && letter.chars().count() > 1 =>
+            {
+                Err(format!(
+                    "{} is several characters, needs --graphemes",
+                    letter
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+impl Default for Matcher {
+    fn default() -> Self {
+        Matcher {
+            segmentation: Segmentation::Chars,
+            case_sensitive: true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Options {
+    matcher: Matcher,
+    // Explain every failing password
+    audit: bool,
+    // Print a summary per letter and range
+    summary: Option<Format>,
+    // Print this many sample passwords per policy instead
+    generate: Option<usize>,
+    seed: u64,
+}
+impl Options {
+    fn from_args(args: impl Iterator<Item = String>) -> Options {
+        let mut options = Options::default();
+        for arg in args {
+            match arg.as_str() {
+                "--graphemes" => options.matcher.segmentation = Segmentation::Graphemes,
+                "--ignore-case" => options.matcher.case_sensitive = false,
+                "--audit" => options.audit = true,
+                "--summary=csv" => options.summary = Some(Format::Csv),
+                "--summary=json" => options.summary = Some(Format::Json),
+                _ if arg.starts_with("--generate=") => {
+                    options.generate = Some(arg[11..].parse().expect("bad --generate"))
+                }
+                _ if arg.starts_with("--seed=") => {
+                    options.seed = arg[7..].parse().expect("bad --seed")
+                }
+                _ => panic!("unknown argument: {}", arg),
+            }
+        }
+        options
+    }
+}
+
+// How the two numbers of a policy are interpreted. A new kind of clause only
+// needs an implementation and an entry in RULES.
+trait PolicyRule {
+    // Names the rule in policy expressions, e.g. "count 2-8 h"
+    fn keyword(&self) -> &'static str;
+
+    fn is_satisfied(&self, policy: &PasswordPolicy, password: &str, matcher: Matcher) -> bool;
+
+    // Why `password` fails the policy
+    fn explain(&self, policy: &PasswordPolicy, _password: &str, _matcher: Matcher) -> String {
+        format!(
+            "fails {} {}-{} {}",
+            self.keyword(),
+            policy.low,
+            policy.high,
+            policy.target
+        )
+    }
+
+    // A password that satisfies the policy, or None if no password can or
+    // the rule has no generator
+    fn generate(&self, _policy: &PasswordPolicy, _generator: &mut Generator) -> Option<String> {
+        None
+    }
+
+    // The password changed as little as possible to flip between valid and
+    // invalid, or None if it cannot flip
+    fn mutate(
+        &self,
+        _policy: &PasswordPolicy,
+        _password: &str,
+        _generator: &mut Generator,
+    ) -> Option<String> {
+        None
+    }
+}
+
+// The letter appears between `low` and `high` times (inclusive)
+struct CountRange;
+impl PolicyRule for CountRange {
+    fn keyword(&self) -> &'static str {
+        "count"
+    }
+
+    fn is_satisfied(&self, policy: &PasswordPolicy, password: &str, matcher: Matcher) -> bool {
+        let count = matcher
+            .letters(password)
+            .iter()
+            .filter(|letter| matcher.is_match(letter, &policy.target))
+            .count();
+
+        policy.low <= count && count <= policy.high
+    }
+
+    fn explain(&self, policy: &PasswordPolicy, password: &str, matcher: Matcher) -> String {
+        audit::explain_count(policy, password, matcher)
+    }
+
+    fn generate(&self, policy: &PasswordPolicy, generator: &mut Generator) -> Option<String> {
+        generator.valid_count(policy)
+    }
+
+    fn mutate(
+        &self,
+        policy: &PasswordPolicy,
+        password: &str,
+        generator: &mut Generator,
+    ) -> Option<String> {
+        generator.mutate_count(policy, password)
+    }
+}
 
-        min <= count && count <= max
+// The letter is at exactly one of the 1-indexed positions `low` and `high`.
+// A position outside the password makes it invalid.
+struct PositionalXor;
+impl PolicyRule for PositionalXor {
+    fn keyword(&self) -> &'static str {
+        "pos"
     }
-    fn validate_qn2(&self, password: &str) -> bool {
-        let mut rule_splits = self.rule.split('-');
-        let (mut pos1, mut pos2) = get_next_two(&mut rule_splits);
 
-        // adjustment
-        pos1 -= 1;
-        pos2 -= 1;
+    fn is_satisfied(&self, policy: &PasswordPolicy, password: &str, matcher: Matcher) -> bool {
+        let letters = matcher.letters(password);
+        let letter_at = |pos: usize| pos.checked_sub(1).and_then(|i| letters.get(i));
 
-        (password[pos1..pos1 + 1] == self.pattern) ^ (password[pos2..pos2 + 1] == self.pattern)
+        match (letter_at(policy.low), letter_at(policy.high)) {
+            (Some(a), Some(b)) => {
+                matcher.is_match(a, &policy.target) ^ matcher.is_match(b, &policy.target)
+            }
+            _ => false,
+        }
+    }
+
+    fn explain(&self, policy: &PasswordPolicy, password: &str, matcher: Matcher) -> String {
+        audit::explain_positions(policy, password, matcher)
+    }
+
+    fn generate(&self, policy: &PasswordPolicy, generator: &mut Generator) -> Option<String> {
+        generator.valid_positions(policy)
+    }
+
+    fn mutate(
+        &self,
+        policy: &PasswordPolicy,
+        password: &str,
+        generator: &mut Generator,
+    ) -> Option<String> {
+        generator.mutate_positions(policy, password)
+    }
+}
+
+// Every rule that can be named in a policy expression
+const RULES: &[Rule] = &[Rule(&CountRange), Rule(&PositionalXor)];
+
+// Bare "min-max c" clauses are counts in part 1 and positions in part 2
+const PARTS: [(&str, Rule); 2] = [
+    ("part 1", Rule(&CountRange)),
+    ("part 2", Rule(&PositionalXor)),
+];
+
+// A registered rule, compared and printed by its keyword
+#[derive(Clone, Copy)]
+struct Rule(&'static dyn PolicyRule);
+impl Rule {
+    fn lookup(keyword: &str) -> Option<Rule> {
+        RULES
+            .iter()
+            .copied()
+            .find(|rule| rule.keyword().eq_ignore_ascii_case(keyword))
+    }
+}
+impl Deref for Rule {
+    type Target = dyn PolicyRule;
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+impl PartialEq for Rule {
+    fn eq(&self, other: &Self) -> bool {
+        self.keyword() == other.keyword()
+    }
+}
+impl fmt::Debug for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Rule({})", self.keyword())
     }
 }
 
 fn main() {
+    let options = Options::from_args(env::args().skip(1));
+    let matcher = options.matcher;
+
     let file = File::open("./src/input.txt").expect("cannot open");
     let reader = BufReader::new(file);
 
-    let pairs: Vec<(PasswordPolicy, String)> = reader
+    let pairs: Vec<(Expr, String)> = reader
         .lines()
         .map(Result::unwrap)
         .map(parse_policy_and_password)
         .collect();
+    for (i, (expr, _)) in pairs.iter().enumerate() {
+        for policy in expr.clauses() {
+            if let Err(err) = matcher.check(&policy.target) {
+                panic!("line {}: {}", i + 1, err);
+            }
+        }
+    }
 
-    let num_valid_part_1 = pairs
-        .iter()
-        .filter(|(password_policy, password)| password_policy.validate_qn1(password))
-        .count();
-    let num_valid_part_2 = pairs
-        .iter()
-        .filter(|(password_policy, password)| password_policy.validate_qn2(password))
-        .count();
+    if let Some(n) = options.generate {
+        let mut generator = Generator::new(options.seed, matcher);
+        for (expr, _) in &pairs {
+            // Only single-clause policies can be generated for
+            if let Expr::Clause(None, policy) = expr {
+                for (label, rule) in PARTS {
+                    for (password, valid) in generator.samples(policy, rule, n) {
+                        let validity = if valid { "valid" } else { "invalid" };
+                        println!("{}\t{}\t{}: {}", label, validity, expr, password);
+                    }
+                }
+            }
+        }
+        return;
+    }
+
+    let [num_valid_part_1, num_valid_part_2] = PARTS.map(|(_, bare)| {
+        pairs
+            .iter()
+            .filter(|(expr, password)| expr.evaluate(password, matcher, bare))
+            .count()
+    });
 
     println!("Part 1: {}", num_valid_part_1);
     println!("Part 2: {}", num_valid_part_2);
-}
 
-fn parse_policy_and_password(line: String) -> (PasswordPolicy, String) {
-    let mut parts = line.split_whitespace();
-    let rule = parts.next().unwrap().to_string();
-    let pattern = parts.next().unwrap()[0..1].to_string();
-    let password = parts.next().unwrap().to_string();
-
-    (PasswordPolicy { rule, pattern }, password)
+    if options.audit {
+        for (i, (expr, password)) in pairs.iter().enumerate() {
+            for (label, bare) in PARTS {
+                for reason in audit::explain(expr, password, matcher, bare) {
+                    println!("line {}: {}: {}: {}", i + 1, expr, label, reason);
+                }
+            }
+        }
+    }
+    if let Some(format) = options.summary {
+        print!("{}", audit::Summary::new(&pairs, matcher).export(format));
+    }
 }
 
-fn get_next_two(rule_splits: &mut Split<char>) -> (usize, usize) {
-    let min = rule_splits.next().unwrap().parse::<usize>().unwrap();
-    let max = rule_splits.next().unwrap().parse::<usize>().unwrap();
-    (min, max)
+fn parse_policy_and_password(line: String) -> (Expr, String) {
+    let (expr, password) = expr::parse(&line).expect("cannot parse policy");
+
+    (expr, password.to_string())
 }
 
 #[cfg(test)]
@@ -74,32 +449,154 @@ mod tests {
         let (policy, password) = parse_policy_and_password("2-8 h: hhqqvhhphhhqddhh".into());
         assert_eq!(
             policy,
-            PasswordPolicy {
-                rule: "2-8".into(),
-                pattern: "h".into()
-            }
+            Expr::Clause(
+                None,
+                PasswordPolicy {
+                    low: 2,
+                    high: 8,
+                    target: Target::Letter("h".into())
+                }
+            )
         );
         assert_eq!(password, "hhqqvhhphhhqddhh");
     }
 
+    #[test]
+    fn test_parse_errors() {
+        assert!("2-8".parse::<PasswordPolicy>().is_err());
+        assert!("2 h".parse::<PasswordPolicy>().is_err());
+        assert!("a-8 h".parse::<PasswordPolicy>().is_err());
+        assert!("2-8 hh".parse::<PasswordPolicy>().is_err());
+        assert!("2-8 é".parse::<PasswordPolicy>().is_ok());
+        assert!("2-8 [a-z]".parse::<PasswordPolicy>().is_ok());
+        assert!("2-8 [z-a]".parse::<PasswordPolicy>().is_err());
+        assert!("2-8 h i".parse::<PasswordPolicy>().is_err());
+    }
+
+    fn policy(s: &str) -> PasswordPolicy {
+        s.parse().unwrap()
+    }
+
     #[test]
     fn it_works() {
-        assert!(PasswordPolicy {
-            rule: "1-3".into(),
-            pattern: "a".into()
-        }
-        .validate_qn1("abcde"));
+        assert!(policy("1-3 a").validate_qn1("abcde", Matcher::default()));
+        assert!(!policy("1-3 b").validate_qn1("cdefg", Matcher::default()));
+        assert!(policy("2-9 c").validate_qn1("ccccccccc", Matcher::default()));
+    }
 
-        assert!(!PasswordPolicy {
-            rule: "1-3".into(),
-            pattern: "b".into()
-        }
-        .validate_qn1("cdefg"));
+    #[test]
+    fn positional_xor() {
+        assert!(policy("1-3 a").validate_qn2("abcde", Matcher::default()));
+        assert!(!policy("1-3 b").validate_qn2("cdefg", Matcher::default()));
+        assert!(!policy("2-9 c").validate_qn2("ccccccccc", Matcher::default()));
+    }
 
-        assert!(PasswordPolicy {
-            rule: "2-9".into(),
-            pattern: "c".into()
+    #[test]
+    fn custom_rule() {
+        // A new rule kind only needs to implement PolicyRule
+        struct MustNotStartWith;
+        impl PolicyRule for MustNotStartWith {
+            fn keyword(&self) -> &'static str {
+                "notfirst"
+            }
+            fn is_satisfied(
+                &self,
+                policy: &PasswordPolicy,
+                password: &str,
+                matcher: Matcher,
+            ) -> bool {
+                !matcher
+                    .letters(password)
+                    .first()
+                    .is_some_and(|first| matcher.is_match(first, &policy.target))
+            }
         }
-        .validate_qn1("ccccccccc"));
+
+        let matcher = Matcher::default();
+        let rule = Rule(&MustNotStartWith);
+        assert!(!rule.is_satisfied(&policy("1-3 a"), "abcde", matcher));
+        assert!(rule.is_satisfied(&policy("1-3 b"), "cdefg", matcher));
+
+        let (expr, _) = expr::parse("1-3 a OR count 2-2 b: pw").unwrap();
+        assert!(!expr.evaluate("abcde", matcher, rule));
+        assert!(expr.evaluate("cbb", matcher, rule));
+        assert_eq!(
+            audit::explain(&expr, "abcde", matcher, rule),
+            vec![
+                "fails notfirst 1-3 a",
+                "letter 'b' appears 1 times, allowed 2-2"
+            ]
+        );
+        assert_eq!(
+            Expr::Clause(Some(rule), policy("1-3 a")).to_string(),
+            "notfirst 1-3 a"
+        );
+        assert_eq!(
+            Generator::new(0, matcher).valid(&policy("1-3 a"), rule),
+            None
+        );
+    }
+
+    #[test]
+    fn unicode_passwords() {
+        let chars = Matcher::default();
+        assert!(policy("1-2 é").validate_qn1("café", chars));
+        assert!(policy("1-4 é").validate_qn2("café", chars));
+        assert!(!policy("1-3 ö").validate_qn2("ööö", chars));
+    }
+
+    #[test]
+    fn grapheme_clusters() {
+        // "e" followed by a combining acute accent
+        let password = "cafe\u{301}x";
+        let chars = Matcher::default();
+        let graphemes = Matcher {
+            segmentation: Segmentation::Graphemes,
+            ..chars
+        };
+
+        assert!(policy("3-4 e").validate_qn2(password, chars));
+        assert!(!policy("3-4 e").validate_qn2(password, graphemes));
+        assert!(!policy("1-5 x").validate_qn2(password, chars));
+        assert!(policy("1-5 x").validate_qn2(password, graphemes));
+        assert!(policy("1-1 e\u{301}").validate_qn1(password, graphemes));
+
+        let accented = policy("1-1 e\u{301}").target;
+        assert!(chars.check(&accented).is_err());
+        assert!(graphemes.check(&accented).is_ok());
+        assert!(chars.check(&policy("1-1 é").target).is_ok());
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let ignore_case = Matcher {
+            case_sensitive: false,
+            ..Matcher::default()
+        };
+        assert!(!policy("2-3 a").validate_qn1("Abab", Matcher::default()));
+        assert!(policy("2-3 a").validate_qn1("Abab", ignore_case));
+        assert!(policy("1-2 A").validate_qn2("ab", ignore_case));
+    }
+
+    #[test]
+    fn out_of_range_positions() {
+        let chars = Matcher::default();
+        assert!(!policy("1-9 a").validate_qn2("abc", chars));
+        assert!(!policy("0-1 a").validate_qn2("abc", chars));
+        assert!(!policy("2-3 é").validate_qn2("é", chars));
+    }
+
+    #[test]
+    fn character_classes() {
+        let chars = Matcher::default();
+        let ignore_case = Matcher {
+            case_sensitive: false,
+            ..chars
+        };
+        assert!(policy("2-3 [0-9]").validate_qn1("a1b2", chars));
+        assert!(policy("2-2 [^a-z]").validate_qn1("a1b2", chars));
+        assert!(policy("1-2 [xyz]").validate_qn2("xa", chars));
+        assert!(!policy("1-2 [A-Z]").validate_qn1("ab1", chars));
+        assert!(policy("1-2 [A-Z]").validate_qn1("ab1", ignore_case));
     }
 }
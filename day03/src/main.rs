@@ -1,69 +1,391 @@
-use std::fs::File;
+use std::env;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
+use std::ops::RangeInclusive;
+use std::time::Instant;
+
+// (right, down)
+type Slope = (usize, usize);
+
+const DEFAULT_SLOPES: [Slope; 5] = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
+
+// e.g. "# tree 1"
+struct Terrain {
+    symbol: char,
+    name: String,
+    cost: i64,
+}
+
+// The terrains a map may contain, in the order they are reported
+struct Legend {
+    terrains: Vec<Terrain>,
+}
+impl Legend {
+    // One terrain per line: symbol, name and cost
+    fn parse(s: &str) -> Result<Legend, String> {
+        let mut terrains: Vec<Terrain> = vec![];
+        for (i, line) in s
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+        {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (symbol, name, cost) = match parts.as_slice() {
+                [symbol, name, cost] if symbol.chars().count() == 1 => (symbol, name, cost),
+                _ => return Err(format!("legend line {}: expected symbol name cost", i + 1)),
+            };
+            let symbol = symbol.chars().next().unwrap();
+            if terrains.iter().any(|terrain| terrain.symbol == symbol) {
+                return Err(format!(
+                    "legend line {}: duplicate symbol '{}'",
+                    i + 1,
+                    symbol
+                ));
+            }
+            let cost = cost
+                .parse()
+                .map_err(|_| format!("legend line {}: bad cost {}", i + 1, cost))?;
+
+            terrains.push(Terrain {
+                symbol,
+                name: name.to_string(),
+                cost,
+            });
+        }
+        if terrains.is_empty() || terrains.len() > u8::MAX as usize {
+            return Err("legend must have between 1 and 255 terrains".to_string());
+        }
+        Ok(Legend { terrains })
+    }
+    fn index(&self, symbol: char) -> Option<u8> {
+        self.terrains
+            .iter()
+            .position(|terrain| terrain.symbol == symbol)
+            .map(|i| i as u8)
+    }
+}
+impl Default for Legend {
+    fn default() -> Self {
+        Legend::parse(". open 0\n# tree 1").unwrap()
+    }
+}
+
+// Each square packed into the fewest bits that index every terrain in the
+// legend, so the default legend takes one bit per square
+struct TerrainMap {
+    width: usize,
+    bits: usize,
+    rows: Vec<Vec<u64>>,
+    legend: Legend,
+}
+impl TerrainMap {
+    fn new(width: usize, legend: Legend) -> TerrainMap {
+        let max_index = legend.terrains.len() - 1;
+        TerrainMap {
+            width,
+            bits: (usize::BITS - max_index.leading_zeros()).max(1) as usize,
+            rows: vec![],
+            legend,
+        }
+    }
+    // Squares never straddle two words
+    fn per_word(&self) -> usize {
+        64 / self.bits
+    }
+    fn push_row(&mut self, terrains: impl Iterator<Item = u8>) {
+        let per_word = self.per_word();
+        let mut words = vec![0; self.width.div_ceil(per_word)];
+        for (col, terrain) in terrains.enumerate() {
+            words[col / per_word] |= (terrain as u64) << (col % per_word * self.bits);
+        }
+        self.rows.push(words);
+    }
+    // Every row must have the same width and only use terrains in the legend
+    fn parse(rows: impl Iterator<Item = String>, legend: Legend) -> Result<TerrainMap, String> {
+        let mut rows = rows.peekable();
+        let width = rows.peek().map_or(0, |row| row.chars().count());
+        if width == 0 {
+            return Err("map is empty".to_string());
+        }
+
+        let mut map = TerrainMap::new(width, legend);
+        for (row, row_of_terrain) in rows.enumerate() {
+            let terrains = row_of_terrain
+                .chars()
+                .enumerate()
+                .map(|(col, symbol)| {
+                    map.legend.index(symbol).ok_or_else(|| {
+                        format!(
+                            "row {}, column {}: unknown terrain '{}'",
+                            row + 1,
+                            col + 1,
+                            symbol
+                        )
+                    })
+                })
+                .collect::<Result<Vec<u8>, String>>()?;
+            if terrains.len() != width {
+                return Err(format!(
+                    "row {} has width {}, expected {}",
+                    row + 1,
+                    terrains.len(),
+                    width
+                ));
+            }
+            map.push_row(terrains.into_iter());
+        }
+        Ok(map)
+    }
+    // The square's index into the legend
+    fn index(&self, row: usize, col: usize) -> u8 {
+        let per_word = self.per_word();
+        let word = self.rows[row][col / per_word] >> (col % per_word * self.bits);
+        (word & ((1 << self.bits) - 1)) as u8
+    }
+    fn terrain(&self, row: usize, col: usize) -> &Terrain {
+        &self.legend.terrains[self.index(row, col) as usize]
+    }
+}
 
 struct TreeCounter {
-    jmp: usize,
+    right: usize,
+    down: usize,
     pos: usize,
     len: usize,
-    count: i64,
+    cost: i64,
+    // Squares landed on, per terrain in the legend
+    hits: Vec<i64>,
 }
 impl TreeCounter {
-    fn new(jmp: usize, len: usize) -> TreeCounter {
+    fn new((right, down): Slope, map: &TerrainMap) -> TreeCounter {
         TreeCounter {
-            jmp,
-            count: 0,
+            right,
+            down,
+            cost: 0,
+            hits: vec![0; map.legend.terrains.len()],
             pos: 0,
-            len,
+            len: map.width,
         }
     }
-    fn inc(&mut self) {
-        self.count += 1;
-    }
-    fn step(&mut self, row_of_trees: &str) {
-        self.pos = (self.pos + self.jmp) % self.len;
-        let c = row_of_trees.chars().nth(self.pos).unwrap();
-        if c == '#' {
-            self.inc();
+    // Rows are numbered from the top, starting at 0
+    fn step(&mut self, row: usize, map: &TerrainMap) {
+        if !row.is_multiple_of(self.down) {
+            return;
         }
+        self.pos = (self.pos + self.right) % self.len;
+        let terrain = map.index(row, self.pos);
+        self.hits[terrain as usize] += 1;
+        self.cost += map.legend.terrains[terrain as usize].cost;
+    }
+    fn breakdown(&self, legend: &Legend) -> String {
+        legend
+            .terrains
+            .iter()
+            .zip(&self.hits)
+            .map(|(terrain, hits)| format!("{} {}", terrain.name, hits))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+// e.g. "3,1"
+fn parse_slope(s: &str) -> Result<Slope, String> {
+    let (right, down) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected right,down: {}", s))?;
+    let right = right.parse().map_err(|_| format!("bad right: {}", s))?;
+    let down = down.parse().map_err(|_| format!("bad down: {}", s))?;
+    if down == 0 {
+        return Err(format!("down must be at least 1: {}", s));
+    }
+    Ok((right, down))
+}
+
+// e.g. "1-7" or "3"
+fn parse_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+    let (start, end) = s.split_once('-').unwrap_or((s, s));
+    let start = start.parse().map_err(|_| format!("bad range: {}", s))?;
+    let end = end.parse().map_err(|_| format!("bad range: {}", s))?;
+    Ok(start..=end)
+}
+
+// e.g. "1-7,1-2" for right 1 to 7 and down 1 to 2
+fn parse_search(s: &str) -> Result<Vec<Slope>, String> {
+    let (rights, downs) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected rights,downs: {}", s))?;
+    let (rights, downs) = (parse_range(rights)?, parse_range(downs)?);
+    if *downs.start() == 0 {
+        return Err(format!("down must be at least 1: {}", s));
     }
-    fn step_if_odd(&mut self, row_of_trees: &str, is_odd: bool) {
-        if is_odd {
-            self.step(&row_of_trees);
+
+    let slopes: Vec<Slope> = rights
+        .flat_map(|right| downs.clone().map(move |down| (right, down)))
+        .collect();
+    if slopes.is_empty() {
+        return Err(format!("no slopes in range: {}", s));
+    }
+    Ok(slopes)
+}
+
+// All slopes with their total costs, cheapest first
+fn rank_slopes(map: &TerrainMap, slopes: &[Slope]) -> Vec<(Slope, i64)> {
+    let mut ranked: Vec<(Slope, i64)> = count_trees(map, slopes)
+        .iter()
+        .map(|counter| ((counter.right, counter.down), counter.cost))
+        .collect();
+    ranked.sort_by_key(|&(slope, cost)| (cost, slope));
+    ranked
+}
+
+// Walks every slope in a single pass over the map
+fn count_trees(map: &TerrainMap, slopes: &[Slope]) -> Vec<TreeCounter> {
+    let mut counters: Vec<TreeCounter> = slopes
+        .iter()
+        .map(|&slope| TreeCounter::new(slope, map))
+        .collect();
+
+    (1..map.rows.len()).for_each(|row| {
+        counters
+            .iter_mut()
+            .for_each(|counter| counter.step(row, map));
+    });
+    counters
+}
+
+// The map tiled as far right as the slope goes, with 'X' where the slope
+// lands on a square that costs something and 'O' where it costs nothing
+fn render(map: &TerrainMap, (right, down): Slope, colour: bool) -> String {
+    let last_col = (map.rows.len().saturating_sub(1) / down) * right;
+    let tiles = last_col / map.width + 1;
+
+    let mut out = String::new();
+    for row in 0..map.rows.len() {
+        let on_path = row > 0 && row.is_multiple_of(down);
+        let path_col = (row / down) * right;
+
+        for col in 0..map.width * tiles {
+            let terrain = map.terrain(row, col % map.width);
+            match (on_path && col == path_col, terrain.cost > 0, colour) {
+                (true, true, true) => out.push_str("\x1b[1;31mX\x1b[0m"),
+                (true, false, true) => out.push_str("\x1b[1;32mO\x1b[0m"),
+                (true, true, false) => out.push('X'),
+                (true, false, false) => out.push('O'),
+                (false, _, _) => out.push(terrain.symbol),
+            };
         }
+        out.push('\n');
     }
+    out
+}
+
+// Counts the default slopes and a 7x7 search on a random 10k x 10k map
+fn bench() {
+    let size = 10_000;
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut map = TerrainMap::new(size, Legend::default());
+
+    let start = Instant::now();
+    for _ in 0..size {
+        map.push_row((0..size).map(|_| {
+            // xorshift64, roughly one tree in four squares
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state.is_multiple_of(4) as u8
+        }));
+    }
+    println!("Generated {}x{} map in {:?}", size, size, start.elapsed());
+
+    let start = Instant::now();
+    let counters = count_trees(&map, &DEFAULT_SLOPES);
+    let total: i64 = counters.iter().map(|counter| counter.cost).sum();
+    println!("Default slopes: {} trees in {:?}", total, start.elapsed());
+
+    let slopes = parse_search("1-7,1-7").unwrap();
+    let start = Instant::now();
+    let ranked = rank_slopes(&map, &slopes);
+    println!("{} slopes ranked in {:?}", ranked.len(), start.elapsed());
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--bench") {
+        bench();
+        return;
+    }
+
+    let legend = match args.iter().find_map(|arg| arg.strip_prefix("--legend=")) {
+        Some(path) => {
+            let legend = fs::read_to_string(path).expect("cannot open legend");
+            Legend::parse(&legend).expect("cannot parse legend")
+        }
+        None => Legend::default(),
+    };
+    let flag = |name: &str| args.iter().find_map(|arg| arg.strip_prefix(name));
+    // Everything else is a slope
+    let slope_args: Vec<&String> = args
+        .iter()
+        .filter(|arg| {
+            !["--legend=", "--render=", "--out=", "--search="]
+                .iter()
+                .any(|flag| arg.starts_with(flag))
+        })
+        .collect();
+
     let file = File::open("./src/input.txt").expect("cannot open file");
-    let mut reader = BufReader::new(file).lines();
-
-    let first_line = reader.next().unwrap().unwrap();
-    let len = first_line.len();
-
-    let mut counter1 = TreeCounter::new(1, len);
-    let mut counter3 = TreeCounter::new(3, len);
-    let mut counter5 = TreeCounter::new(5, len);
-    let mut counter7 = TreeCounter::new(7, len);
-    let mut counter1odd = TreeCounter::new(1, len);
-    let mut is_odd = false;
-
-    reader.map(Result::unwrap).for_each(|row_of_trees| {
-        counter1.step(&row_of_trees);
-        counter3.step(&row_of_trees);
-        counter5.step(&row_of_trees);
-        counter7.step(&row_of_trees);
-        counter1odd.step_if_odd(&row_of_trees, is_odd);
-        is_odd = !is_odd;
-    });
-    println!("No. of trees: {}", counter1.count);
-    println!("No. of trees: {}", counter3.count);
-    println!("No. of trees: {}", counter5.count);
-    println!("No. of trees: {}", counter7.count);
-    println!("No. of trees: {}", counter1odd.count);
+    let map = TerrainMap::parse(BufReader::new(file).lines().map(Result::unwrap), legend)
+        .expect("invalid map");
+
+    if let Some(slope) = flag("--render=") {
+        let slope = parse_slope(slope).expect("cannot parse slope");
+
+        match flag("--out=") {
+            Some(path) => fs::write(path, render(&map, slope, false)).expect("cannot write file"),
+            None => print!("{}", render(&map, slope, true)),
+        }
+        return;
+    }
+
+    if let Some(search) = flag("--search=") {
+        let slopes = parse_search(search).expect("cannot parse search");
+
+        let ranked = rank_slopes(&map, &slopes);
+        for ((right, down), cost) in &ranked {
+            println!("Right {}, down {}: cost {}", right, down, cost);
+        }
+        let ((right, down), cost) = ranked[0];
+        println!(
+            "Lowest cost: right {}, down {} (cost {})",
+            right, down, cost
+        );
+        return;
+    }
+
+    let slopes: Vec<Slope> = if slope_args.is_empty() {
+        DEFAULT_SLOPES.to_vec()
+    } else {
+        slope_args
+            .iter()
+            .map(|arg| parse_slope(arg).expect("cannot parse slope"))
+            .collect()
+    };
+
+    let counters = count_trees(&map, &slopes);
+    for counter in &counters {
+        println!(
+            "Right {}, down {}: cost {} ({})",
+            counter.right,
+            counter.down,
+            counter.cost,
+            counter.breakdown(&map.legend)
+        );
+    }
 
     println!(
         "Product: {}",
-        counter1.count * counter3.count * counter5.count * counter7.count * counter1odd.count
+        counters.iter().map(|counter| counter.cost).product::<i64>()
     );
 }
 
@@ -71,34 +393,155 @@ fn main() {
 mod tests {
     use super::*;
 
+    fn test_map() -> TerrainMap {
+        let file = File::open("./src/test.txt").expect("cannot open file");
+        let rows = BufReader::new(file).lines().map(Result::unwrap);
+        TerrainMap::parse(rows, Legend::default()).unwrap()
+    }
+
+    fn map_of(rows: &[&str], legend: Legend) -> Result<TerrainMap, String> {
+        TerrainMap::parse(rows.iter().map(|row| row.to_string()), legend)
+    }
+
     #[test]
     fn it_works() {
-        let file = File::open("./src/test.txt").expect("cannot open file");
-        let mut reader = BufReader::new(file).lines();
-
-        let first_line = reader.next().unwrap().unwrap();
-        let len = first_line.len();
-
-        let mut trees1 = TreeCounter::new(1, len);
-        let mut trees3 = TreeCounter::new(3, len);
-        let mut trees5 = TreeCounter::new(5, len);
-        let mut trees7 = TreeCounter::new(7, len);
-        let mut trees1odd = TreeCounter::new(1, len);
-        let mut is_odd = false;
-
-        reader.map(Result::unwrap).for_each(|row_of_trees| {
-            trees1.step(&row_of_trees);
-            trees3.step(&row_of_trees);
-            trees5.step(&row_of_trees);
-            trees7.step(&row_of_trees);
-            trees1odd.step_if_odd(&row_of_trees, is_odd);
-            is_odd = !is_odd;
-        });
-
-        assert_eq!(trees1.count, 2);
-        assert_eq!(trees3.count, 7);
-        assert_eq!(trees5.count, 3);
-        assert_eq!(trees7.count, 4);
-        assert_eq!(trees1odd.count, 2);
+        let counts: Vec<i64> = count_trees(&test_map(), &DEFAULT_SLOPES)
+            .iter()
+            .map(|counter| counter.cost)
+            .collect();
+
+        assert_eq!(counts, vec![2, 7, 3, 4, 2]);
+        assert_eq!(counts.iter().product::<i64>(), 336);
+    }
+
+    #[test]
+    fn steeper_slopes() {
+        let counts: Vec<i64> = count_trees(&test_map(), &[(1, 3), (2, 3), (0, 1)])
+            .iter()
+            .map(|counter| counter.cost)
+            .collect();
+
+        assert_eq!(counts, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn parsing_slopes() {
+        assert_eq!(parse_slope("3,1"), Ok((3, 1)));
+        assert_eq!(parse_slope("1,2"), Ok((1, 2)));
+        assert!(parse_slope("1,0").is_err());
+        assert!(parse_slope("1").is_err());
+        assert!(parse_slope("a,1").is_err());
+    }
+
+    #[test]
+    fn searching_slopes() {
+        let slopes = parse_search("0-7,1-3").unwrap();
+        assert_eq!(slopes.len(), 24);
+
+        let ranked = rank_slopes(&test_map(), &slopes);
+        assert_eq!(ranked.len(), 24);
+        assert_eq!(ranked[0], ((1, 3), 0));
+        assert!(ranked.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert!(ranked.contains(&((3, 1), 7)));
+        assert!(ranked.contains(&((2, 3), 1)));
+
+        assert!(parse_search("1-3,0-2").is_err());
+        assert!(parse_search("3-1,1").is_err());
+        assert_eq!(parse_search("3,1"), Ok(vec![(3, 1)]));
+    }
+
+    #[test]
+    fn terrain_costs() {
+        let legend = fs::read_to_string("./src/legend.txt").unwrap();
+        let map = map_of(
+            &["....", "#^..", ".*^.", "^.#*", ".#.."],
+            Legend::parse(&legend).unwrap(),
+        )
+        .unwrap();
+
+        let counters = count_trees(&map, &[(1, 1), (3, 1)]);
+        assert_eq!(counters[0].hits, vec![1, 0, 2, 1]);
+        assert_eq!(counters[0].cost, 5);
+        assert_eq!(
+            counters[0].breakdown(&map.legend),
+            "open 1, tree 0, rock 2, snow 1"
+        );
+        assert_eq!(counters[1].hits, vec![3, 0, 1, 0]);
+        assert_eq!(counters[1].cost, 2);
+    }
+
+    #[test]
+    fn packed_rows() {
+        let mut map = TerrainMap::new(130, Legend::default());
+        map.push_row((0..130).map(|col| (col % 64 == 63 || col == 129) as u8));
+
+        assert_eq!(map.bits, 1);
+        assert_eq!(map.rows[0].len(), 3);
+        assert_eq!(map.index(0, 63), 1);
+        assert_eq!(map.index(0, 127), 1);
+        assert_eq!(map.index(0, 129), 1);
+        assert_eq!(map.index(0, 0), 0);
+        assert_eq!(map.index(0, 64), 0);
+        assert_eq!(map.index(0, 128), 0);
+
+        // Five terrains take three bits, 21 squares to a word
+        let legend = Legend::parse(". open 0\n# tree 1\n^ rock 2\n* snow 1\n~ ice 3").unwrap();
+        let mut map = TerrainMap::new(130, legend);
+        map.push_row((0..130).map(|col| (col % 5) as u8));
+
+        assert_eq!(map.bits, 3);
+        assert_eq!(map.rows[0].len(), 7);
+        assert!((0..130).all(|col| map.index(0, col) == (col % 5) as u8));
+        assert_eq!(map.terrain(0, 24).name, "ice");
+    }
+
+    #[test]
+    fn invalid_maps() {
+        assert_eq!(
+            map_of(&["..#.", "#..", ".#.#"], Legend::default()).err(),
+            Some("row 2 has width 3, expected 4".to_string())
+        );
+        assert_eq!(
+            map_of(&["..#.", "#.^."], Legend::default()).err(),
+            Some("row 2, column 3: unknown terrain '^'".to_string())
+        );
+        assert!(map_of(&[], Legend::default()).is_err());
+
+        assert!(Legend::parse("# tree").is_err());
+        assert!(Legend::parse("# tree x").is_err());
+        assert!(Legend::parse("# tree 1\n# rock 2").is_err());
+        assert!(Legend::parse("").is_err());
+    }
+
+    #[test]
+    fn rendering_path() {
+        let map = map_of(&["..#.", "#...", ".#.#", "..#.", "#..."], Legend::default()).unwrap();
+
+        assert_eq!(
+            render(&map, (3, 1), false),
+            "..#...#...#...#.\n\
+             #..O#...#...#...\n\
+             .#.#.#O#.#.#.#.#\n\
+             ..#...#..O#...#.\n\
+             #...#...#...X...\n"
+        );
+        assert_eq!(
+            render(&map, (1, 2), false),
+            "..#.\n\
+             #...\n\
+             .X.#\n\
+             ..#.\n\
+             #.O.\n"
+        );
+        assert!(render(&map, (3, 1), true).contains("\x1b[1;31mX\x1b[0m"));
+
+        let legend = Legend::parse(". open 0\n# tree 1\n* snow 0").unwrap();
+        let map = map_of(&["..", "#*", "*#"], legend).unwrap();
+        assert_eq!(render(&map, (1, 1), false), "....\n#O#*\n*#O#\n");
+
+        // Every tree hit is drawn
+        let rendered = render(&test_map(), (3, 1), false);
+        assert_eq!(rendered.matches('X').count(), 7);
+        assert_eq!(rendered.lines().next().unwrap().len(), 66);
     }
 }
@@ -0,0 +1,316 @@
+use crate::is_valid_passport;
+use crate::passport::Record;
+use crate::schema::Schema;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+// One row per passport with a column per schema field, then whether the
+// passport is valid. Fields outside the schema are left out.
+pub fn export(records: &[Record], schema: &Schema, format: Format) -> String {
+    let keys: Vec<&str> = schema
+        .fields
+        .iter()
+        .map(|field| field.name.as_str())
+        .collect();
+    let mut out = String::new();
+
+    match format {
+        Format::Csv => {
+            out.push_str(&format!("index,{},valid\n", keys.join(",")));
+            for record in records {
+                let values: Vec<String> = keys
+                    .iter()
+                    .map(|key| csv_field(record.value(key).unwrap_or("")))
+                    .collect();
+                out.push_str(&format!(
+                    "{},{},{}\n",
+                    record.index,
+                    values.join(","),
+                    is_valid_passport(record, schema)
+                ));
+            }
+        }
+        // JSON lines, leaving out missing fields
+        Format::Json => {
+            for record in records {
+                out.push_str(&format!("{{\"index\": {}", record.index));
+                for key in &keys {
+                    if let Some(value) = record.value(key) {
+                        out.push_str(&format!(", {}: {}", json_string(key), json_string(value)));
+                    }
+                }
+                out.push_str(&format!(
+                    ", \"valid\": {}}}\n",
+                    is_valid_passport(record, schema)
+                ));
+            }
+        }
+    }
+    out
+}
+
+// Two passports sharing a pid that disagree on a field
+#[derive(PartialEq, Debug)]
+pub struct Conflict {
+    pub pid: String,
+    pub key: String,
+    pub first: (usize, String),
+    pub second: (usize, String),
+}
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "pid {}: {} is {} in passport {} but {} in passport {}",
+            self.pid, self.key, self.first.1, self.first.0, self.second.1, self.second.0
+        )
+    }
+}
+
+// Passports sharing a pid, whether or not they agree
+#[derive(PartialEq, Debug)]
+pub struct Duplicate {
+    pub pid: String,
+    pub records: Vec<usize>,
+}
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let records: Vec<String> = self.records.iter().map(usize::to_string).collect();
+        write!(
+            f,
+            "pid {} appears in passports {}",
+            self.pid,
+            records.join(", ")
+        )
+    }
+}
+
+// Records with a pid, grouped by it, in input order
+fn by_pid(records: &[Record]) -> BTreeMap<&str, Vec<&Record>> {
+    let mut groups: BTreeMap<&str, Vec<&Record>> = BTreeMap::new();
+    for record in records {
+        if let Some(pid) = record.value("pid") {
+            groups.entry(pid).or_default().push(record);
+        }
+    }
+    groups
+}
+
+fn group_conflicts(pid: &str, group: &[&Record]) -> Vec<Conflict> {
+    let mut conflicts = vec![];
+    for (i, first) in group.iter().enumerate() {
+        for second in &group[i + 1..] {
+            for field in &first.fields {
+                let (value, other) = match (&field.value, second.value(&field.key)) {
+                    (Some(value), Some(other)) => (value, other),
+                    _ => continue,
+                };
+                if value != other && first.value(&field.key) == Some(value) {
+                    conflicts.push(Conflict {
+                        pid: pid.to_string(),
+                        key: field.key.clone(),
+                        first: (first.index, value.clone()),
+                        second: (second.index, other.to_string()),
+                    });
+                }
+            }
+        }
+    }
+    conflicts
+}
+
+pub fn duplicates(records: &[Record]) -> Vec<Duplicate> {
+    by_pid(records)
+        .into_iter()
+        .filter(|(_, group)| group.len() > 1)
+        .map(|(pid, group)| Duplicate {
+            pid: pid.to_string(),
+            records: group.iter().map(|record| record.index).collect(),
+        })
+        .collect()
+}
+
+pub fn conflicts(records: &[Record]) -> Vec<Conflict> {
+    by_pid(records)
+        .iter()
+        .flat_map(|(pid, group)| group_conflicts(pid, group))
+        .collect()
+}
+
+// Whether no field is given different values in the two records
+fn compatible(a: &Record, b: &Record) -> bool {
+    b.fields
+        .iter()
+        .all(|field| match (a.value(&field.key), b.value(&field.key)) {
+            (Some(first), Some(second)) => first == second,
+            _ => true,
+        })
+}
+
+// Folds each passport into the first earlier one with the same pid that it
+// does not conflict with, so compatible duplicates merge even when others
+// sharing the pid conflict. Merged fields keep the passport they came from.
+pub fn merge(records: Vec<Record>) -> Vec<Record> {
+    let mut merged: Vec<Record> = vec![];
+    for record in records {
+        let target = record.value("pid").and_then(|pid| {
+            merged
+                .iter_mut()
+                .find(|other| other.value("pid") == Some(pid) && compatible(other, &record))
+        });
+        match target {
+            Some(target) => {
+                for field in record.fields {
+                    if !target.has_key(&field.key) {
+                        target.fields.push(field);
+                    }
+                }
+            }
+            None => merged.push(record),
+        }
+    }
+    merged
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::passport::{diagnose, parse_records};
+    use crate::DEFAULT_SCHEMA;
+
+    fn records(s: &str) -> Vec<Record> {
+        parse_records(s.lines().map(String::from))
+    }
+
+    #[test]
+    fn exports() {
+        let schema = Schema::parse("byr range 1900 1950\ncid optional any").unwrap();
+        let records = records("byr:1920 cid:a,b\n\nbyr:1990");
+
+        assert_eq!(
+            export(&records, &schema, Format::Csv),
+            "index,byr,cid,valid\n\
+             1,1920,\"a,b\",true\n\
+             2,1990,,false\n"
+        );
+        assert_eq!(
+            export(&records, &schema, Format::Json),
+            "{\"index\": 1, \"byr\": \"1920\", \"cid\": \"a,b\", \"valid\": true}\n\
+             {\"index\": 2, \"byr\": \"1990\", \"valid\": false}\n"
+        );
+    }
+
+    #[test]
+    fn duplicates_and_conflicts() {
+        let records = records(
+            "pid:1 byr:1937\n\n\
+             pid:2 byr:1950\n\n\
+             pid:1 byr:1940 hgt:170cm\n\n\
+             pid:2 hgt:180cm\n\n\
+             byr:1960",
+        );
+
+        let found = conflicts(&records);
+        assert_eq!(
+            found,
+            vec![Conflict {
+                pid: "1".into(),
+                key: "byr".into(),
+                first: (1, "1937".into()),
+                second: (3, "1940".into()),
+            }]
+        );
+        assert_eq!(
+            found[0].to_string(),
+            "pid 1: byr is 1937 in passport 1 but 1940 in passport 3"
+        );
+
+        let found = duplicates(&records);
+        assert_eq!(
+            found,
+            vec![
+                Duplicate {
+                    pid: "1".into(),
+                    records: vec![1, 3],
+                },
+                Duplicate {
+                    pid: "2".into(),
+                    records: vec![2, 4],
+                },
+            ]
+        );
+        assert_eq!(found[1].to_string(), "pid 2 appears in passports 2, 4");
+
+        let merged = merge(records);
+        let indexes: Vec<usize> = merged.iter().map(|record| record.index).collect();
+        assert_eq!(indexes, vec![1, 2, 3, 5]);
+        assert_eq!(merged[1].value("byr"), Some("1950"));
+        assert_eq!(merged[1].value("hgt"), Some("180cm"));
+        assert_eq!(merged[1].fields.len(), 3);
+    }
+
+    #[test]
+    fn partial_merges() {
+        let records = records(
+            "pid:1 byr:1937
+
+             pid:1 byr:1940
+
+             pid:1 hgt:170cm
+             byr:1937 foo:bar",
+        );
+        // The third passport merges into the first, the second stays apart
+        let merged = merge(records);
+        let indexes: Vec<usize> = merged.iter().map(|record| record.index).collect();
+        assert_eq!(indexes, vec![1, 2]);
+        assert_eq!(merged[0].value("hgt"), Some("170cm"));
+        assert_eq!(merged[1].fields.len(), 2);
+
+        let foo = merged[0].fields.iter().find(|f| f.key == "foo").unwrap();
+        assert_eq!((foo.record, foo.line), (3, 6));
+        let messages: Vec<String> = diagnose(&merged[0], &DEFAULT_SCHEMA)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            messages[1],
+            "passport 1, line 6 of passport 3: unknown key foo"
+        );
+        assert_eq!(messages[2], "passport 1, line 1: missing field iyr");
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(csv_field("a\"b"), "\"a\"\"b\"");
+        assert_eq!(json_string("\"\\"), r#""\"\\""#);
+    }
+}
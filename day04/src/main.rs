@@ -1,272 +1,283 @@
+use export::{conflicts, duplicates, export, merge, Format};
 use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashSet;
-use std::fs::File;
+use passport::{diagnose, parse_records, Passport, Record};
+use profile::{Matrix, Profile};
+use schema::Schema;
+use std::env;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
 
-const DELIMITER: &str = ":";
+mod export;
+mod passport;
+mod profile;
+mod schema;
+
 lazy_static! {
-    static ref ALL_FIELD_LABELS: HashSet<&'static str> =
-        ["byr", "eyr", "iyr", "hgt", "pid", "hcl", "ecl"]
-            .iter()
-            .cloned()
-            .collect();
+    static ref DEFAULT_SCHEMA: Schema =
+        Schema::parse(include_str!("schema.txt")).expect("invalid default schema");
 }
 
 fn main() {
-    part1();
-    part2();
-}
-
-fn part1() {
-    let mut num_valid = 0;
-
-    let file = File::open("./src/input.txt").expect("cannot open file");
-    let reader = BufReader::new(file);
-
-    let mut passport_buffer = String::from("");
-    reader.lines().map(Result::unwrap).for_each(|line| {
-        if line.is_empty() {
-            // Process the buffer
-            if has_required_fields(&passport_buffer) {
-                num_valid += 1;
-            }
-            passport_buffer.clear();
-        } else {
-            passport_buffer.push(' ');
-            passport_buffer.push_str(&line);
-        }
+    let args: Vec<String> = env::args().skip(1).collect();
+    let custom_schema = args.iter().find_map(|arg| {
+        let path = arg.strip_prefix("--schema=")?.to_string();
+        let schema = fs::read_to_string(path).expect("cannot open schema");
+        Some(Schema::parse(&schema).expect("cannot parse schema"))
     });
+    let schema = custom_schema.as_ref().unwrap_or(&DEFAULT_SCHEMA);
 
-    if has_required_fields(&passport_buffer) {
-        num_valid += 1;
-        passport_buffer.clear();
+    let mut records = read_records();
+    if args.iter().any(|arg| arg == "--dedupe") {
+        for duplicate in duplicates(&records) {
+            println!("{}", duplicate);
+        }
+        for conflict in conflicts(&records) {
+            println!("{}", conflict);
+        }
+    }
+    if args.iter().any(|arg| arg == "--merge") {
+        records = merge(records);
     }
 
-    println!("Part 1: {}", num_valid);
-}
+    let matrix = Matrix::new(&records, &Profile::defaults(schema));
+    println!("Part 1: {}", matrix.passing("fields present").unwrap());
+    println!("Part 2: {}", matrix.passing("strict").unwrap());
 
-fn part2() {
-    let mut num_valid = 0;
+    if args.iter().any(|arg| arg == "--matrix") {
+        print!("{}", matrix);
+    }
+    if args.iter().any(|arg| arg == "--report") {
+        report(&records, schema);
+    }
+    if args.iter().any(|arg| arg == "--valid") {
+        print_valid(&records, schema);
+    }
+    let format = args
+        .iter()
+        .find_map(|arg| match arg.strip_prefix("--export=")? {
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            other => panic!("unknown export format {}", other),
+        });
+    if let Some(format) = format {
+        print!("{}", export(&records, schema, format));
+    }
+}
 
+fn read_records() -> Vec<Record> {
     let file = File::open("./src/input.txt").expect("cannot open file");
     let reader = BufReader::new(file);
+    parse_records(reader.lines().map(Result::unwrap))
+}
 
-    let mut passport_buffer = String::from("");
-    reader.lines().map(Result::unwrap).for_each(|line| {
-        if line.is_empty() {
-            // Process the buffer
-            if is_valid_passport(&passport_buffer) {
-                num_valid += 1;
-            }
-            passport_buffer.clear();
-        } else {
-            passport_buffer.push(' ');
-            passport_buffer.push_str(&line);
+// Every problem with every passport, not just the first
+fn report(records: &[Record], schema: &Schema) {
+    let mut num_invalid = 0;
+    for record in records {
+        let diagnostics = diagnose(record, schema);
+        if !diagnostics.is_empty() {
+            num_invalid += 1;
+        }
+        for diagnostic in diagnostics {
+            println!("{}", diagnostic);
         }
-    });
-
-    if is_valid_passport(&passport_buffer) {
-        num_valid += 1;
-        passport_buffer.clear();
     }
-
-    println!("Part 2: {}", num_valid);
+    println!("{} of {} passports invalid", num_invalid, records.len());
 }
 
-fn has_required_fields(passport: &str) -> bool {
-    let field_labels: HashSet<&str> = passport
-        .split_whitespace()
-        .map(|field_label| field_label.split(DELIMITER).next().expect("problem"))
-        .filter(|&field_label| field_label != "cid")
-        .collect();
-    ALL_FIELD_LABELS.is_subset(&field_labels)
-}
-
-fn is_valid_passport(passport: &str) -> bool {
-    let mut available_fields: HashSet<&str> = HashSet::new();
-
-    let fields = passport.split_whitespace();
-    for field in fields {
-        let mut field_iter = field.split(DELIMITER);
-        let field_label = field_iter.next().expect("problem");
-        let field_value = field_iter.next().expect("problem");
-
-        let valid = match field_label {
-            "byr" => validate_byr(field_value),
-            "eyr" => validate_eyr(field_value),
-            "iyr" => validate_iyr(field_value),
-            "hgt" => validate_hgt(field_value),
-            "pid" => validate_pid(field_value),
-            "hcl" => validate_hcl(field_value),
-            "ecl" => validate_ecl(field_value),
-            "cid" => true,
-            _ => panic!("unknown field: {}", field_label),
-        };
-        if !valid {
-            return false;
-        };
-
-        available_fields.insert(field_label);
-    }
-    if !ALL_FIELD_LABELS.is_subset(&available_fields) {
-        return false;
+// Valid passports in normalised key:value form, one per line
+fn print_valid(records: &[Record], schema: &Schema) {
+    for record in records {
+        if let Ok(passport) = Passport::from_record(record, schema) {
+            println!("{}", passport);
+        }
     }
-    true
 }
 
-fn validate_byr(input: &str) -> bool {
-    let year = input.parse::<i32>().unwrap();
-    (1920..=2002).contains(&year)
-}
-fn validate_iyr(input: &str) -> bool {
-    let year = input.parse::<i32>().unwrap();
-    (2010..=2020).contains(&year)
-}
-fn validate_eyr(input: &str) -> bool {
-    let year = input.parse::<i32>().unwrap();
-    (2020..=2030).contains(&year)
-}
-fn validate_hgt(input: &str) -> bool {
-    if input.ends_with("cm") {
-        let height = input[0..input.len() - 2].parse::<i32>().unwrap();
-        (150..=193).contains(&height)
-    } else if input.ends_with("in") {
-        let height = input[0..input.len() - 2].parse::<i32>().unwrap();
-        (59..=76).contains(&height)
-    } else {
-        false
-    }
-}
-fn validate_hcl(input: &str) -> bool {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"^#[0-9a-f]{6}$").unwrap();
-    }
-    RE.is_match(input)
-}
-fn validate_pid(input: &str) -> bool {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"^\d{9}$").unwrap();
-    }
-    RE.is_match(input)
+fn has_required_fields(record: &Record, schema: &Schema) -> bool {
+    schema
+        .required_fields()
+        .all(|field_label| record.has_key(field_label))
 }
-fn validate_ecl(input: &str) -> bool {
-    lazy_static! {
-        static ref ARR: &'static [&'static str] =
-            &["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
-    }
-    ARR.contains(&input)
+
+fn is_valid_passport(record: &Record, schema: &Schema) -> bool {
+    diagnose(record, schema).is_empty()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn record(passport: &str) -> Record {
+        let mut records = parse_records(passport.lines().map(String::from));
+        assert_eq!(records.len(), 1);
+        records.remove(0)
+    }
+
+    fn validate(field_label: &str, value: &str) -> bool {
+        DEFAULT_SCHEMA
+            .field(field_label)
+            .unwrap()
+            .rule
+            .check(value)
+            .is_ok()
+    }
+
     #[test]
     fn validating_byr() {
-        assert!(validate_byr("1920"));
-        assert!(validate_byr("2002"));
-        assert!(validate_byr("2000"));
-        assert!(!validate_byr("2020"));
+        assert!(validate("byr", "1920"));
+        assert!(validate("byr", "2002"));
+        assert!(validate("byr", "2000"));
+        assert!(!validate("byr", "2020"));
     }
 
     #[test]
     fn validating_iyr() {
-        assert!(validate_iyr("2010"));
-        assert!(validate_iyr("2020"));
-        assert!(validate_iyr("2015"));
-        assert!(!validate_iyr("2030"));
+        assert!(validate("iyr", "2010"));
+        assert!(validate("iyr", "2020"));
+        assert!(validate("iyr", "2015"));
+        assert!(!validate("iyr", "2030"));
     }
 
     #[test]
     fn validating_eyr() {
-        assert!(validate_eyr("2020"));
-        assert!(validate_eyr("2030"));
-        assert!(validate_eyr("2025"));
-        assert!(!validate_eyr("2050"));
+        assert!(validate("eyr", "2020"));
+        assert!(validate("eyr", "2030"));
+        assert!(validate("eyr", "2025"));
+        assert!(!validate("eyr", "2050"));
     }
 
     #[test]
     fn validating_hgt() {
-        assert!(validate_hgt("150cm"));
-        assert!(validate_hgt("193cm"));
-        assert!(validate_hgt("180cm"));
-        assert!(validate_hgt("76in"));
-        assert!(validate_hgt("59in"));
-        assert!(!validate_hgt("gibberish"));
+        assert!(validate("hgt", "150cm"));
+        assert!(validate("hgt", "193cm"));
+        assert!(validate("hgt", "180cm"));
+        assert!(validate("hgt", "76in"));
+        assert!(validate("hgt", "59in"));
+        assert!(!validate("hgt", "gibberish"));
     }
 
     #[test]
     fn validating_pid() {
-        assert!(validate_pid("123456789"));
-        assert!(!validate_pid("123"));
+        assert!(validate("pid", "123456789"));
+        assert!(!validate("pid", "123"));
     }
 
     #[test]
     fn validating_hcl() {
-        assert!(validate_hcl("#003366"));
-        assert!(validate_hcl("#00f14e"));
-        assert!(!validate_hcl("#1234567"));
-        assert!(!validate_hcl("1234567"));
-        assert!(!validate_hcl("#gggggg"));
+        assert!(validate("hcl", "#003366"));
+        assert!(validate("hcl", "#00f14e"));
+        assert!(!validate("hcl", "#1234567"));
+        assert!(!validate("hcl", "1234567"));
+        assert!(!validate("hcl", "#gggggg"));
     }
 
     #[test]
     fn validating_ecl() {
-        assert!(validate_ecl("amb"));
-        assert!(!validate_ecl("AMB"));
+        assert!(validate("ecl", "amb"));
+        assert!(!validate("ecl", "AMB"));
     }
 
     #[test]
     fn check_passports2() {
         assert!(is_valid_passport(
-            "iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719"
+            &record("iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719"),
+            &DEFAULT_SCHEMA
         ));
         assert!(is_valid_passport(
-            r#"eyr:2029 ecl:blu cid:129 byr:1989
+            &record(
+                r#"eyr:2029 ecl:blu cid:129 byr:1989
     iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm"#
+            ),
+            &DEFAULT_SCHEMA
         ));
         assert!(is_valid_passport(
-            r#"pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+            &record(
+                r#"pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
     hcl:#623a2f"#
+            ),
+            &DEFAULT_SCHEMA
         ));
 
         assert!(!is_valid_passport(
-            r#"eyr:1972 cid:100
+            &record(
+                r#"eyr:1972 cid:100
     hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926"#
+            ),
+            &DEFAULT_SCHEMA
         ));
         assert!(!is_valid_passport(
-            r#"iyr:2019
+            &record(
+                r#"iyr:2019
     hcl:#602927 eyr:1967 hgt:170cm
     ecl:grn pid:012533040 byr:1946"#
+            ),
+            &DEFAULT_SCHEMA
         ));
         assert!(!is_valid_passport(
-            r#"hcl:dab227 iyr:2012
+            &record(
+                r#"hcl:dab227 iyr:2012
     ecl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277"#
+            ),
+            &DEFAULT_SCHEMA
         ));
         assert!(!is_valid_passport(
-            r#"hgt:59cm ecl:zzz
+            &record(
+                r#"hgt:59cm ecl:zzz
     eyr:2038 hcl:74454a iyr:2023
     pid:3556412378 byr:2007"#
+            ),
+            &DEFAULT_SCHEMA
         ));
 
         assert!(has_required_fields(
-            r#"ecl:gry pid:860033327 eyr:2020 hcl:#fffffd
+            &record(
+                r#"ecl:gry pid:860033327 eyr:2020 hcl:#fffffd
         byr:1937 iyr:2017 cid:147 hgt:183cm"#
+            ),
+            &DEFAULT_SCHEMA
         ));
         assert!(!has_required_fields(
-            r#"iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884
+            &record(
+                r#"iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884
         hcl:#cfa07d byr:1929"#
+            ),
+            &DEFAULT_SCHEMA
         ));
         assert!(has_required_fields(
-            r#"hcl:#ae17e1 iyr:2013
+            &record(
+                r#"hcl:#ae17e1 iyr:2013
                 eyr:2024
                 ecl:brn pid:760753108 byr:1931
                 hgt:179cm"#
+            ),
+            &DEFAULT_SCHEMA
         ));
         assert!(!has_required_fields(
-            r#"hcl:#cfa07d eyr:2025 pid:166559648
-                iyr:2011 ecl:brn hgt:59in"#,
+            &record(
+                r#"hcl:#cfa07d eyr:2025 pid:166559648
+                iyr:2011 ecl:brn hgt:59in"#
+            ),
+            &DEFAULT_SCHEMA
         ));
     }
+
+    #[test]
+    fn custom_schema() {
+        let schema = Schema::parse("byr range 1900 1950\ncid any").unwrap();
+        assert!(is_valid_passport(&record("byr:1900 cid:1"), &schema));
+        assert!(!is_valid_passport(&record("byr:1990 cid:1"), &schema));
+        assert!(!is_valid_passport(&record("byr:1900"), &schema));
+        assert!(!is_valid_passport(&record("byr:1900 cid:1 pid:1"), &schema));
+        assert!(has_required_fields(&record("byr:1 cid:1"), &schema));
+        assert!(!has_required_fields(&record("cid:1"), &schema));
+    }
+
+    #[test]
+    fn bad_passports_do_not_panic() {
+        assert!(!is_valid_passport(&record("byr:abc"), &DEFAULT_SCHEMA));
+        assert!(!is_valid_passport(&record("foo:bar"), &DEFAULT_SCHEMA));
+        assert!(!is_valid_passport(&record("byr"), &DEFAULT_SCHEMA));
+        assert!(!has_required_fields(&record("byr iyr"), &DEFAULT_SCHEMA));
+    }
 }
@@ -0,0 +1,506 @@
+use crate::schema::{Problem, Schema};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+const DELIMITER: char = ':';
+
+#[derive(PartialEq, Debug)]
+pub struct Field {
+    pub key: String,
+    // None when the field has no delimiter
+    pub value: Option<String>,
+    // The passport the field was written in and its line there. The passport
+    // differs from the field's record once records are merged.
+    pub record: usize,
+    pub line: usize,
+}
+
+// A passport as written, before it is checked against a schema
+#[derive(PartialEq, Debug)]
+pub struct Record {
+    // Both start at 1
+    pub index: usize,
+    pub line: usize,
+    pub fields: Vec<Field>,
+}
+impl Record {
+    pub fn has_key(&self, key: &str) -> bool {
+        self.fields.iter().any(|field| field.key == key)
+    }
+
+    // The first value given for `key`
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|field| field.key == key)
+            .and_then(|field| field.value.as_deref())
+    }
+}
+
+// Passports are separated by blank lines
+pub fn parse_records(lines: impl Iterator<Item = String>) -> Vec<Record> {
+    let mut records: Vec<Record> = vec![];
+    let mut current: Option<Record> = None;
+
+    for (i, line) in lines.enumerate() {
+        if line.trim().is_empty() {
+            records.extend(current.take());
+            continue;
+        }
+        let record = current.get_or_insert_with(|| Record {
+            index: records.len() + 1,
+            line: i + 1,
+            fields: vec![],
+        });
+        for field in line.split_whitespace() {
+            let (key, value) = match field.split_once(DELIMITER) {
+                Some((key, value)) => (key, Some(value.to_string())),
+                None => (field, None),
+            };
+            record.fields.push(Field {
+                key: key.to_string(),
+                value,
+                record: record.index,
+                line: i + 1,
+            });
+        }
+    }
+    records.extend(current.take());
+    records
+}
+
+#[derive(PartialEq, Debug)]
+pub enum DiagnosticKind {
+    MissingField(String),
+    MissingDelimiter(String),
+    MalformedValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+    OutOfRange {
+        key: String,
+        value: String,
+        reason: String,
+    },
+    DuplicateKey(String),
+    UnknownKey(String),
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Diagnostic {
+    pub record: usize,
+    // The passport `line` is in, which differs from `record` for fields
+    // merged in from another passport
+    pub source: usize,
+    pub line: usize,
+    pub kind: DiagnosticKind,
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.source == self.record {
+            write!(f, "passport {}, line {}: ", self.record, self.line)?;
+        } else {
+            write!(
+                f,
+                "passport {}, line {} of passport {}: ",
+                self.record, self.line, self.source
+            )?;
+        }
+        match &self.kind {
+            DiagnosticKind::MissingField(key) => write!(f, "missing field {}", key),
+            DiagnosticKind::MissingDelimiter(field) => {
+                write!(f, "'{}' is not a key:value field", field)
+            }
+            DiagnosticKind::MalformedValue { key, value, reason } => {
+                write!(f, "{}: malformed value '{}', {}", key, value, reason)
+            }
+            DiagnosticKind::OutOfRange { key, value, reason } => {
+                write!(f, "{}: '{}' is out of range, {}", key, value, reason)
+            }
+            DiagnosticKind::DuplicateKey(key) => write!(f, "duplicate key {}", key),
+            DiagnosticKind::UnknownKey(key) => write!(f, "unknown key {}", key),
+        }
+    }
+}
+
+// Everything wrong with a record, in the order it was written
+pub fn diagnose(record: &Record, schema: &Schema) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut report = |(source, line): (usize, usize), kind: DiagnosticKind| {
+        diagnostics.push(Diagnostic {
+            record: record.index,
+            source,
+            line,
+            kind,
+        })
+    };
+
+    for field in &record.fields {
+        let key = field.key.clone();
+        let at = (field.record, field.line);
+        let value = match &field.value {
+            Some(value) => value,
+            None => {
+                report(at, DiagnosticKind::MissingDelimiter(key));
+                continue;
+            }
+        };
+        if !seen.insert(&field.key) {
+            report(at, DiagnosticKind::DuplicateKey(key));
+            continue;
+        }
+        let field_rule = match schema.field(&field.key) {
+            Some(field_rule) => field_rule,
+            None => {
+                report(at, DiagnosticKind::UnknownKey(key));
+                continue;
+            }
+        };
+        let value = value.clone();
+        match field_rule.rule.check(&value) {
+            Ok(()) => {}
+            Err(Problem::Malformed(reason)) => {
+                report(at, DiagnosticKind::MalformedValue { key, value, reason })
+            }
+            Err(Problem::OutOfRange(reason)) => {
+                report(at, DiagnosticKind::OutOfRange { key, value, reason })
+            }
+        }
+    }
+
+    for key in schema.required_fields() {
+        if !record.has_key(key) {
+            report(
+                (record.index, record.line),
+                DiagnosticKind::MissingField(key.to_string()),
+            );
+        }
+    }
+    diagnostics
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Unit {
+    Cm,
+    In,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Height {
+    pub value: u32,
+    pub unit: Unit,
+}
+impl FromStr for Height {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = if let Some(value) = s.strip_suffix("cm") {
+            (value, Unit::Cm)
+        } else if let Some(value) = s.strip_suffix("in") {
+            (value, Unit::In)
+        } else {
+            return Err(format!("bad height unit: {}", s));
+        };
+        let value = value.parse().map_err(|_| format!("bad height: {}", s))?;
+        Ok(Height { value, unit })
+    }
+}
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = match self.unit {
+            Unit::Cm => "cm",
+            Unit::In => "in",
+        };
+        write!(f, "{}{}", self.value, unit)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EyeColour {
+    Amber,
+    Blue,
+    Brown,
+    Grey,
+    Green,
+    Hazel,
+    Other,
+}
+const EYE_COLOURS: [(EyeColour, &str); 7] = [
+    (EyeColour::Amber, "amb"),
+    (EyeColour::Blue, "blu"),
+    (EyeColour::Brown, "brn"),
+    (EyeColour::Grey, "gry"),
+    (EyeColour::Green, "grn"),
+    (EyeColour::Hazel, "hzl"),
+    (EyeColour::Other, "oth"),
+];
+impl FromStr for EyeColour {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EYE_COLOURS
+            .iter()
+            .find(|(_, code)| *code == s)
+            .map(|(colour, _)| *colour)
+            .ok_or_else(|| format!("bad eye colour: {}", s))
+    }
+}
+impl fmt::Display for EyeColour {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (_, code) = EYE_COLOURS
+            .iter()
+            .find(|(colour, _)| colour == self)
+            .unwrap();
+        write!(f, "{}", code)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("bad colour: {}", s);
+        let hex = s.strip_prefix('#').ok_or_else(err)?;
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(err());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| err());
+        Ok(Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+}
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+// Kept as text so leading zeros survive
+#[derive(Clone, PartialEq, Debug)]
+pub struct PassportId(String);
+impl FromStr for PassportId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("bad passport id: {}", s));
+        }
+        Ok(PassportId(s.to_string()))
+    }
+}
+impl fmt::Display for PassportId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// A passport that has passed its schema, with typed fields
+#[derive(Clone, PartialEq, Debug)]
+pub struct Passport {
+    pub birth_year: u16,
+    pub issue_year: u16,
+    pub expiration_year: u16,
+    pub height: Height,
+    pub hair_colour: Rgb,
+    pub eye_colour: EyeColour,
+    pub passport_id: PassportId,
+    pub country_id: Option<String>,
+}
+impl Passport {
+    // The schema may be stricter than the types, but not looser
+    pub fn from_record(record: &Record, schema: &Schema) -> Result<Passport, String> {
+        let diagnostics = diagnose(record, schema);
+        if !diagnostics.is_empty() {
+            let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+            return Err(messages.join("; "));
+        }
+
+        let value = |key: &str| record.value(key);
+        fn typed<T: FromStr>(key: &str, value: Option<&str>) -> Result<T, String>
+        where
+            T::Err: fmt::Display,
+        {
+            let value = value.ok_or_else(|| format!("missing field {}", key))?;
+            value.parse().map_err(|e| format!("{}: {}", key, e))
+        }
+
+        Ok(Passport {
+            birth_year: typed("byr", value("byr"))?,
+            issue_year: typed("iyr", value("iyr"))?,
+            expiration_year: typed("eyr", value("eyr"))?,
+            height: typed("hgt", value("hgt"))?,
+            hair_colour: typed("hcl", value("hcl"))?,
+            eye_colour: typed("ecl", value("ecl"))?,
+            passport_id: typed("pid", value("pid"))?,
+            country_id: value("cid").map(String::from),
+        })
+    }
+}
+// One passport in key:value format, checked against the default schema
+impl FromStr for Passport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut records = parse_records(s.lines().map(String::from));
+        if records.len() != 1 {
+            return Err(format!("expected one passport, found {}", records.len()));
+        }
+        Passport::from_record(&records.remove(0), &crate::DEFAULT_SCHEMA)
+    }
+}
+impl fmt::Display for Passport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "byr:{} iyr:{} eyr:{} hgt:{} hcl:{} ecl:{} pid:{}",
+            self.birth_year,
+            self.issue_year,
+            self.expiration_year,
+            self.height,
+            self.hair_colour,
+            self.eye_colour,
+            self.passport_id
+        )?;
+        if let Some(cid) = &self.country_id {
+            write!(f, " cid:{}", cid)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_SCHEMA;
+
+    fn records(s: &str) -> Vec<Record> {
+        parse_records(s.lines().map(String::from))
+    }
+
+    #[test]
+    fn parsing_records() {
+        let records = records("\nbyr:1920 cid\niyr:2010\n\n\npid:1\n");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].index, 1);
+        assert_eq!(records[0].line, 2);
+        assert_eq!(
+            records[0].fields[1],
+            Field {
+                key: "cid".into(),
+                value: None,
+                record: 1,
+                line: 2
+            }
+        );
+        assert_eq!(records[0].fields[2].line, 3);
+        assert_eq!(records[1].index, 2);
+        assert_eq!(records[1].line, 6);
+    }
+
+    #[test]
+    fn diagnostics() {
+        let records = records(
+            "byr:abc iyr:2010 eyr:2040\n\
+             hgt:170 hcl:#123abc ecl:zzz pid:000000001\n\
+             iyr:2011 foo:bar cid\n\
+             \n\
+             ecl:amb",
+        );
+
+        let messages: Vec<String> = diagnose(&records[0], &DEFAULT_SCHEMA)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "passport 1, line 1: byr: malformed value 'abc', expected a number",
+                "passport 1, line 1: eyr: '2040' is out of range, expected 2020 to 2030",
+                "passport 1, line 2: hgt: malformed value '170', expected a unit of cm, in",
+                "passport 1, line 2: ecl: 'zzz' is out of range, expected one of amb, blu, brn, gry, grn, hzl, oth",
+                "passport 1, line 3: duplicate key iyr",
+                "passport 1, line 3: unknown key foo",
+                "passport 1, line 3: 'cid' is not a key:value field",
+            ]
+        );
+
+        let missing = diagnose(&records[1], &DEFAULT_SCHEMA);
+        assert_eq!(missing.len(), 6);
+        assert_eq!(
+            missing[0],
+            Diagnostic {
+                record: 2,
+                source: 2,
+                line: 5,
+                kind: DiagnosticKind::MissingField("byr".into())
+            }
+        );
+    }
+
+    #[test]
+    fn typed_passports() {
+        let passport: Passport = "pid:087499704 hgt:74in ecl:grn iyr:2012\n\
+                                  eyr:2030 byr:1980 hcl:#623a2f"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            passport.height,
+            Height {
+                value: 74,
+                unit: Unit::In
+            }
+        );
+        assert_eq!(passport.eye_colour, EyeColour::Green);
+        assert_eq!(
+            passport.hair_colour,
+            Rgb {
+                r: 0x62,
+                g: 0x3a,
+                b: 0x2f
+            }
+        );
+        assert_eq!(passport.passport_id.to_string(), "087499704");
+        assert_eq!(passport.country_id, None);
+
+        let normalised = passport.to_string();
+        assert_eq!(
+            normalised,
+            "byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#623a2f ecl:grn pid:087499704"
+        );
+        assert_eq!(normalised.parse::<Passport>(), Ok(passport));
+
+        let with_cid =
+            "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147";
+        assert_eq!(with_cid.parse::<Passport>().unwrap().to_string(), with_cid);
+    }
+
+    #[test]
+    fn untyped_passports() {
+        let err = "byr:1980".parse::<Passport>().unwrap_err();
+        assert!(err.starts_with("passport 1, line 1: missing field iyr; "));
+        assert!("hgt:59cm ecl:zzz".parse::<Passport>().is_err());
+        assert!("".parse::<Passport>().is_err());
+        assert!("byr:1980\n\nbyr:1981".parse::<Passport>().is_err());
+
+        // A schema looser than the types is caught by the types
+        let schema =
+            Schema::parse("byr any\niyr any\neyr any\nhgt any\nhcl any\necl any\npid any").unwrap();
+        let record = &records("byr:1980 iyr:2012 eyr:2030 hgt:74 hcl:#623a2f ecl:grn pid:1")[0];
+        assert_eq!(
+            Passport::from_record(record, &schema),
+            Err("hgt: bad height unit: 74".into())
+        );
+    }
+}
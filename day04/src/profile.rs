@@ -0,0 +1,147 @@
+use crate::passport::Record;
+use crate::schema::Schema;
+use crate::{has_required_fields, is_valid_passport};
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Level {
+    // Required fields are present, whatever their values
+    Present,
+    // Every field is known, well-formed and in range
+    Strict,
+}
+
+// A named way of judging passports
+pub struct Profile {
+    pub name: String,
+    pub level: Level,
+    pub schema: Schema,
+}
+impl Profile {
+    pub fn new(name: &str, level: Level, schema: Schema) -> Profile {
+        Profile {
+            name: name.to_string(),
+            level,
+            schema,
+        }
+    }
+
+    // Part 1, part 2, and part 2 without cid being optional
+    pub fn defaults(schema: &Schema) -> Vec<Profile> {
+        let mut profiles = vec![
+            Profile::new("fields present", Level::Present, schema.clone()),
+            Profile::new("strict", Level::Strict, schema.clone()),
+        ];
+        if let Some(with_cid) = schema.requiring("cid") {
+            profiles.push(Profile::new(
+                "strict with cid required",
+                Level::Strict,
+                with_cid,
+            ));
+        }
+        profiles
+    }
+
+    pub fn accepts(&self, record: &Record) -> bool {
+        match self.level {
+            Level::Present => has_required_fields(record, &self.schema),
+            Level::Strict => is_valid_passport(record, &self.schema),
+        }
+    }
+}
+
+// Passport index by profile result
+pub struct Matrix {
+    pub profiles: Vec<String>,
+    pub rows: Vec<(usize, Vec<bool>)>,
+}
+impl Matrix {
+    pub fn new(records: &[Record], profiles: &[Profile]) -> Matrix {
+        Matrix {
+            profiles: profiles
+                .iter()
+                .map(|profile| profile.name.clone())
+                .collect(),
+            rows: records
+                .iter()
+                .map(|record| {
+                    let results = profiles.iter().map(|p| p.accepts(record)).collect();
+                    (record.index, results)
+                })
+                .collect(),
+        }
+    }
+
+    // How many passports the profile accepts
+    pub fn passing(&self, name: &str) -> Option<usize> {
+        let column = self.profiles.iter().position(|profile| profile == name)?;
+        Some(
+            self.rows
+                .iter()
+                .filter(|(_, results)| results[column])
+                .count(),
+        )
+    }
+}
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "passport")?;
+        for profile in &self.profiles {
+            write!(f, "  {}", profile)?;
+        }
+        writeln!(f)?;
+        for (index, results) in &self.rows {
+            let mut row = format!("{:<8}", index);
+            for (profile, &accepted) in self.profiles.iter().zip(results) {
+                let result = if accepted { "yes" } else { "no" };
+                row.push_str(&format!("  {:<width$}", result, width = profile.len()));
+            }
+            writeln!(f, "{}", row.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::passport::parse_records;
+    use crate::DEFAULT_SCHEMA;
+
+    #[test]
+    fn default_profiles() {
+        let records = parse_records(
+            "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147\n\
+             \n\
+             byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327\n\
+             \n\
+             byr:1937 iyr:2017 eyr:2020 hgt:183 hcl:#fffffd ecl:gry pid:860033327\n\
+             \n\
+             byr:1937"
+                .lines()
+                .map(String::from),
+        );
+        let matrix = Matrix::new(&records, &Profile::defaults(&DEFAULT_SCHEMA));
+
+        assert_eq!(
+            matrix.rows,
+            vec![
+                (1, vec![true, true, true]),
+                (2, vec![true, true, false]),
+                (3, vec![true, false, false]),
+                (4, vec![false, false, false]),
+            ]
+        );
+        assert_eq!(matrix.passing("fields present"), Some(3));
+        assert_eq!(matrix.passing("strict with cid required"), Some(1));
+        assert_eq!(matrix.passing("lenient"), None);
+        assert_eq!(
+            matrix.to_string().lines().take(3).collect::<Vec<_>>(),
+            vec![
+                "passport  fields present  strict  strict with cid required",
+                "1         yes             yes     yes",
+                "2         yes             yes     no",
+            ]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+// Passport rules, one field per line:
+//
+//     <field> [optional] <kind> <args...>
+//
+// where kind is one of
+//
+//     any
+//     range <min> <max>
+//     units <unit> <min> <max> [<unit> <min> <max>...]
+//     regex <pattern>
+//     enum <value> [<value>...]
+//
+// Fields are required unless marked optional. Blank lines and lines
+// starting with "//" are ignored. Patterns cannot contain whitespace.
+
+use regex::Regex;
+
+// Why a value breaks a rule
+#[derive(PartialEq, Debug)]
+pub enum Problem {
+    Malformed(String),
+    OutOfRange(String),
+}
+
+#[derive(Clone, Debug)]
+pub enum Rule {
+    Any,
+    Range(i64, i64),
+    Units(Vec<(String, i64, i64)>),
+    Regex(Regex),
+    Enum(Vec<String>),
+}
+impl Rule {
+    pub fn check(&self, value: &str) -> Result<(), Problem> {
+        let in_range = |n: i64, min: i64, max: i64| {
+            if (min..=max).contains(&n) {
+                Ok(())
+            } else {
+                Err(Problem::OutOfRange(format!("expected {} to {}", min, max)))
+            }
+        };
+        match self {
+            Rule::Any => Ok(()),
+            Rule::Range(min, max) => match value.parse::<i64>() {
+                Ok(n) => in_range(n, *min, *max),
+                Err(_) => Err(Problem::Malformed("expected a number".to_string())),
+            },
+            Rule::Units(units) => {
+                let unit = units.iter().find_map(|(unit, min, max)| {
+                    Some((value.strip_suffix(unit.as_str())?, min, max))
+                });
+                match unit {
+                    Some((n, min, max)) => match n.parse::<i64>() {
+                        Ok(n) => in_range(n, *min, *max),
+                        Err(_) => Err(Problem::Malformed("expected a number".to_string())),
+                    },
+                    None => {
+                        let names: Vec<&str> =
+                            units.iter().map(|(unit, _, _)| unit.as_str()).collect();
+                        Err(Problem::Malformed(format!(
+                            "expected a unit of {}",
+                            names.join(", ")
+                        )))
+                    }
+                }
+            }
+            Rule::Regex(re) if re.is_match(value) => Ok(()),
+            Rule::Regex(re) => Err(Problem::Malformed(format!("expected /{}/", re))),
+            Rule::Enum(values) if values.iter().any(|v| v == value) => Ok(()),
+            Rule::Enum(values) => Err(Problem::OutOfRange(format!(
+                "expected one of {}",
+                values.join(", ")
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FieldRule {
+    pub name: String,
+    pub required: bool,
+    pub rule: Rule,
+}
+
+#[derive(Clone, Debug)]
+pub struct Schema {
+    pub fields: Vec<FieldRule>,
+}
+impl Schema {
+    pub fn parse(s: &str) -> Result<Schema, String> {
+        let mut fields: Vec<FieldRule> = vec![];
+
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            let err = |msg: &str| format!("schema line {}: {}", i + 1, msg);
+
+            let mut parts = line.split_whitespace();
+            let name = parts.next().unwrap().to_string();
+            if fields.iter().any(|field| field.name == name) {
+                return Err(err(&format!("duplicate field {}", name)));
+            }
+            let mut kind = parts.next().ok_or_else(|| err("missing rule"))?;
+            let required = kind != "optional";
+            if !required {
+                kind = parts.next().ok_or_else(|| err("missing rule"))?;
+            }
+
+            let args: Vec<&str> = parts.collect();
+            let number = |s: &str| {
+                s.parse::<i64>()
+                    .map_err(|_| err(&format!("bad number {}", s)))
+            };
+            let rule = match (kind, args.as_slice()) {
+                ("any", []) => Rule::Any,
+                ("range", [min, max]) => Rule::Range(number(min)?, number(max)?),
+                ("units", units) if !units.is_empty() && units.len() % 3 == 0 => Rule::Units(
+                    units
+                        .chunks(3)
+                        .map(|unit| Ok((unit[0].to_string(), number(unit[1])?, number(unit[2])?)))
+                        .collect::<Result<_, String>>()?,
+                ),
+                ("regex", [pattern]) => {
+                    Rule::Regex(Regex::new(pattern).map_err(|e| err(&e.to_string()))?)
+                }
+                ("enum", values) if !values.is_empty() => {
+                    Rule::Enum(values.iter().map(|v| v.to_string()).collect())
+                }
+                _ => return Err(err(&format!("bad rule: {}", line))),
+            };
+
+            fields.push(FieldRule {
+                name,
+                required,
+                rule,
+            });
+        }
+        Ok(Schema { fields })
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldRule> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    // The same rules with `name` required, or None if there is no such field
+    pub fn requiring(&self, name: &str) -> Option<Schema> {
+        let mut schema = self.clone();
+        schema
+            .fields
+            .iter_mut()
+            .find(|field| field.name == name)?
+            .required = true;
+        Some(schema)
+    }
+
+    pub fn required_fields(&self) -> impl Iterator<Item = &str> {
+        self.fields
+            .iter()
+            .filter(|field| field.required)
+            .map(|field| field.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rules() {
+        let schema = Schema::parse(
+            "// comment\n\
+             byr range 1920 2002\n\n\
+             hgt units cm 150 193 in 59 76\n\
+             cid optional any",
+        )
+        .unwrap();
+
+        assert_eq!(schema.fields.len(), 3);
+        assert_eq!(
+            schema.required_fields().collect::<Vec<_>>(),
+            vec!["byr", "hgt"]
+        );
+        assert!(schema.field("hgt").unwrap().rule.check("60in").is_ok());
+        assert!(schema.field("hgt").unwrap().rule.check("60cm").is_err());
+        assert!(schema.field("cid").unwrap().rule.check("anything").is_ok());
+        assert!(schema.field("pid").is_none());
+
+        let strict = schema.requiring("cid").unwrap();
+        assert_eq!(
+            strict.required_fields().collect::<Vec<_>>(),
+            vec!["byr", "hgt", "cid"]
+        );
+        assert!(schema.requiring("pid").is_none());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Schema::parse("byr").is_err());
+        assert!(Schema::parse("byr range 1920").is_err());
+        assert!(Schema::parse("byr range abc 2002").is_err());
+        assert!(Schema::parse("hgt units cm 150").is_err());
+        assert!(Schema::parse("hcl regex [").is_err());
+        assert!(Schema::parse("ecl enum").is_err());
+        assert!(Schema::parse("byr any\nbyr any").is_err());
+        assert!(Schema::parse("byr optional").is_err());
+        assert!(Schema::parse("byr between 1 2").is_err());
+    }
+
+    #[test]
+    fn malformed_values() {
+        let range = Rule::Range(1920, 2002);
+        assert_eq!(
+            range.check("abc"),
+            Err(Problem::Malformed("expected a number".into()))
+        );
+        assert_eq!(
+            range.check("1900"),
+            Err(Problem::OutOfRange("expected 1920 to 2002".into()))
+        );
+
+        let units = Rule::Units(vec![("cm".into(), 150, 193), ("in".into(), 59, 76)]);
+        assert_eq!(
+            units.check("cm"),
+            Err(Problem::Malformed("expected a number".into()))
+        );
+        assert_eq!(
+            units.check("170"),
+            Err(Problem::Malformed("expected a unit of cm, in".into()))
+        );
+        assert_eq!(
+            units.check("80in"),
+            Err(Problem::OutOfRange("expected 59 to 76".into()))
+        );
+
+        let colours = Rule::Enum(vec!["amb".into(), "blu".into()]);
+        assert_eq!(
+            colours.check("zzz"),
+            Err(Problem::OutOfRange("expected one of amb, blu".into()))
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+pub type SeatID = i32;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Seat {
+    pub row: u32,
+    pub column: u32,
+}
+
+// A cabin of 2^row_bits rows by 2^column_bits columns. Each letter of a
+// boarding pass picks the lower or upper half of what is left, so a pass
+// is just the seat's row and column written in binary.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Layout {
+    pub row_bits: u32,
+    pub column_bits: u32,
+    // (lower, upper)
+    pub row_letters: (char, char),
+    pub column_letters: (char, char),
+}
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            row_bits: 7,
+            column_bits: 3,
+            row_letters: ('F', 'B'),
+            column_letters: ('L', 'R'),
+        }
+    }
+}
+impl Layout {
+    pub fn rows(&self) -> u32 {
+        1 << self.row_bits
+    }
+
+    pub fn columns(&self) -> u32 {
+        1 << self.column_bits
+    }
+
+    pub fn seat_id(&self, seat: Seat) -> SeatID {
+        (seat.row << self.column_bits | seat.column) as SeatID
+    }
+
+    // The seat with this ID, if the cabin has one
+    pub fn seat(&self, id: SeatID) -> Option<Seat> {
+        let id = u32::try_from(id).ok()?;
+        let seat = Seat {
+            row: id >> self.column_bits,
+            column: id & ((1 << self.column_bits) - 1),
+        };
+        if seat.row >= self.rows() {
+            return None;
+        }
+        Some(seat)
+    }
+
+    pub fn encode(&self, seat: Seat) -> Result<String, String> {
+        if seat.row >= self.rows() || seat.column >= self.columns() {
+            return Err(format!(
+                "no seat at row {}, column {} in a {}x{} cabin",
+                seat.row,
+                seat.column,
+                self.rows(),
+                self.columns()
+            ));
+        }
+        let mut pass = letters(seat.row, self.row_bits, self.row_letters);
+        pass.push_str(&letters(seat.column, self.column_bits, self.column_letters));
+        Ok(pass)
+    }
+
+    pub fn encode_id(&self, id: SeatID) -> Result<String, String> {
+        let seat = self
+            .seat(id)
+            .ok_or_else(|| format!("no seat with ID {}", id))?;
+        self.encode(seat)
+    }
+
+    pub fn decode(&self, pass: &str) -> Result<Seat, PassError> {
+        let letters: Vec<char> = pass.chars().collect();
+        let row_bits = self.row_bits as usize;
+
+        // A pass for a different layout is reported by its length, rather
+        // than by the first letter that ends up out of place
+        let expected = row_bits + self.column_bits as usize;
+        if letters.len() != expected {
+            return Err(PassError::Length {
+                expected,
+                found: letters.len(),
+            });
+        }
+        for (index, &letter) in letters.iter().enumerate() {
+            let expected = if index < row_bits {
+                self.row_letters
+            } else {
+                self.column_letters
+            };
+            if letter == expected.0 || letter == expected.1 {
+                continue;
+            }
+            let known = [self.row_letters, self.column_letters]
+                .iter()
+                .any(|&(lower, upper)| letter == lower || letter == upper);
+            return Err(if known {
+                PassError::Position {
+                    index,
+                    letter,
+                    expected,
+                }
+            } else {
+                PassError::Letter { index, letter }
+            });
+        }
+        let (rows, columns) = letters.split_at(row_bits);
+        Ok(Seat {
+            row: bits(rows, self.row_letters),
+            column: bits(columns, self.column_letters),
+        })
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum PassError {
+    Length {
+        expected: usize,
+        found: usize,
+    },
+    // Not a letter of this layout at all
+    Letter {
+        index: usize,
+        letter: char,
+    },
+    // A row letter among the columns or the other way round
+    Position {
+        index: usize,
+        letter: char,
+        expected: (char, char),
+    },
+}
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PassError::Length { expected, found } => {
+                write!(f, "expected {} letters, found {}", expected, found)
+            }
+            PassError::Letter { index, letter } => {
+                write!(
+                    f,
+                    "index {}: {:?} is not a boarding pass letter",
+                    index, letter
+                )
+            }
+            PassError::Position {
+                index,
+                letter,
+                expected: (lower, upper),
+            } => write!(
+                f,
+                "index {}: {:?} does not belong here, expected {} or {}",
+                index, letter, lower, upper
+            ),
+        }
+    }
+}
+// "7,3" or "7,3,FB,LR"
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        let number = |s: &str| {
+            s.parse::<u32>()
+                .map_err(|_| format!("bad bit count: {}", s))
+        };
+        let letters = |s: &str| {
+            let letters: Vec<char> = s.chars().collect();
+            match letters.as_slice() {
+                [lower, upper] if lower != upper => Ok((*lower, *upper)),
+                _ => Err(format!("expected two different letters: {}", s)),
+            }
+        };
+
+        let default = Layout::default();
+        let layout = match parts.as_slice() {
+            [rows, columns] => Layout {
+                row_bits: number(rows)?,
+                column_bits: number(columns)?,
+                ..default
+            },
+            [rows, columns, row_letters, column_letters] => Layout {
+                row_bits: number(rows)?,
+                column_bits: number(columns)?,
+                row_letters: letters(row_letters)?,
+                column_letters: letters(column_letters)?,
+            },
+            _ => return Err(format!("bad layout: {}", s)),
+        };
+        // Seat IDs must fit in a SeatID
+        if layout.row_bits + layout.column_bits > 30 {
+            return Err(format!("layout too large: {}", s));
+        }
+        Ok(layout)
+    }
+}
+
+// Letters must already be checked to be lower or upper
+fn bits(letters: &[char], (_, upper): (char, char)) -> u32 {
+    letters
+        .iter()
+        .fold(0, |n, &letter| n << 1 | (letter == upper) as u32)
+}
+
+// The lowest `count` bits of `n`, most significant first
+fn letters(n: u32, count: u32, (lower, upper): (char, char)) -> String {
+    (0..count)
+        .rev()
+        .map(|bit| if n >> bit & 1 == 0 { lower } else { upper })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_layout() {
+        let layout = Layout::default();
+        assert_eq!(layout.decode("FBFBBFFRLR"), Ok(Seat { row: 44, column: 5 }));
+    }
+
+    #[test]
+    fn custom_layouts() {
+        let layout: Layout = "3,2,AB,XY".parse().unwrap();
+        let seat = layout.decode("BABYX").unwrap();
+        assert_eq!(seat, Seat { row: 5, column: 2 });
+        assert_eq!(layout.seat_id(seat), 22);
+
+        assert_eq!("6,2".parse::<Layout>().unwrap().row_letters, ('F', 'B'));
+        assert!("6".parse::<Layout>().is_err());
+        assert!("6,x".parse::<Layout>().is_err());
+        assert!("6,2,FF,LR".parse::<Layout>().is_err());
+        assert!("20,20".parse::<Layout>().is_err());
+    }
+
+    // Passes that used to decode quietly or panic
+    #[test]
+    fn foreign_letters() {
+        let layout = Layout::default();
+        assert_eq!(
+            layout.decode("XXXXXXXXXX"),
+            Err(PassError::Letter {
+                index: 0,
+                letter: 'X'
+            })
+        );
+
+        let layout: Layout = "3,2,AB,XY".parse().unwrap();
+        assert_eq!(
+            layout.decode("AB"),
+            Err(PassError::Length {
+                expected: 5,
+                found: 2
+            })
+        );
+        assert_eq!(
+            layout.decode("ABAFL"),
+            Err(PassError::Letter {
+                index: 3,
+                letter: 'F'
+            })
+        );
+    }
+
+    #[test]
+    fn encoding() {
+        let layout = Layout::default();
+        assert_eq!(layout.encode_id(357), Ok("FBFBBFFRLR".into()));
+        assert_eq!(
+            layout.encode(Seat {
+                row: 102,
+                column: 4
+            }),
+            Ok("BBFFBBFRLL".into())
+        );
+        assert_eq!(layout.seat(1024), None);
+        assert_eq!(layout.seat(-1), None);
+        assert!(layout.encode_id(1024).is_err());
+        assert_eq!(
+            layout.encode(Seat { row: 0, column: 8 }),
+            Err("no seat at row 0, column 8 in a 128x8 cabin".into())
+        );
+    }
+
+    #[test]
+    fn invalid_passes() {
+        let layout = Layout::default();
+        assert_eq!(
+            layout.decode("FBF"),
+            Err(PassError::Length {
+                expected: 10,
+                found: 3
+            })
+        );
+        assert_eq!(
+            layout.decode("FBFBBFFRLRL"),
+            Err(PassError::Length {
+                expected: 10,
+                found: 11
+            })
+        );
+        assert_eq!(
+            layout.decode("FBFxBFFRLR"),
+            Err(PassError::Letter {
+                index: 3,
+                letter: 'x'
+            })
+        );
+        assert_eq!(
+            layout.decode("FBFBBFFRLF").unwrap_err().to_string(),
+            "index 9: 'F' does not belong here, expected L or R"
+        );
+        assert_eq!(
+            layout.decode("FBFBBFRRLR").unwrap_err().to_string(),
+            "index 6: 'R' does not belong here, expected F or B"
+        );
+        assert_eq!(
+            layout.decode("").unwrap_err().to_string(),
+            "expected 10 letters, found 0"
+        );
+    }
+}
@@ -1,87 +1,133 @@
+use layout::{Layout, PassError, Seat, SeatID};
+use simulator::{Cabin, Passenger, Simulator};
+use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use vacancy::Report;
 
-type SeatID = i32;
+mod layout;
+mod simulator;
+mod vacancy;
 
 pub fn main() {
-    let path = "./src/input.txt";
-    qn1(path);
-    qn2(path);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let layout: Layout = args
+        .iter()
+        .find_map(|arg| Some(arg.strip_prefix("--layout=")?.parse().expect("bad layout")))
+        .unwrap_or_default();
+
+    // --encode=ID or --encode=ROW,COLUMN prints a boarding pass
+    if let Some(seat) = args.iter().find_map(|arg| arg.strip_prefix("--encode=")) {
+        let pass = match seat.split_once(',') {
+            Some((row, column)) => layout.encode(Seat {
+                row: row.parse().expect("bad row"),
+                column: column.parse().expect("bad column"),
+            }),
+            None => layout.encode_id(seat.parse().expect("bad seat ID")),
+        };
+        println!("{}", pass.unwrap_or_else(|e| panic!("{}", e)));
+        return;
+    }
+
+    // --simulate=FILE boards the passengers in FILE onto an empty cabin
+    if let Some(path) = args.iter().find_map(|arg| arg.strip_prefix("--simulate=")) {
+        let aisles = args.iter().find_map(|arg| {
+            let aisles = arg.strip_prefix("--aisles=")?.split(',');
+            Some(aisles.map(|a| a.parse().expect("bad aisle")).collect())
+        });
+        let cabin = match aisles {
+            Some(aisles) => Cabin::new(layout, aisles).unwrap_or_else(|e| panic!("{}", e)),
+            None => Cabin::with_middle_aisle(layout),
+        };
+        simulate(path, cabin);
+        return;
+    }
+
+    let (seats, out_of_range) = read_seat_ids("./src/input.txt", &layout);
+    qn1(&seats);
+    let report = Report::new(&layout, &seats, out_of_range);
+    qn2(&report, args.iter().any(|arg| arg == "--map"));
 }
 
-fn qn1(path: &str) {
+// Decodes every pass, reporting and skipping the bad ones. Passes too long or
+// too short for the layout are also returned with their line numbers.
+fn read_seat_ids(path: &str, layout: &Layout) -> (Vec<SeatID>, Vec<(usize, String)>) {
     let file = File::open(path).expect("cannot open file");
     let reader = BufReader::new(file);
 
-    let mut max_seat_id = 0;
+    let mut seats = vec![];
+    let mut out_of_range = vec![];
+    let mut num_bad = 0;
+    for (i, line) in reader.lines().map(Result::unwrap).enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match get_seat_id(line.trim(), layout) {
+            Ok(seat) => seats.push(seat),
+            Err(e) => {
+                eprintln!("line {}: {}", i + 1, e);
+                num_bad += 1;
+                if let PassError::Length { .. } = e {
+                    out_of_range.push((i + 1, line.trim().to_string()));
+                }
+            }
+        }
+    }
+    if num_bad > 0 {
+        eprintln!("skipped {} bad boarding passes", num_bad);
+    }
+    (seats, out_of_range)
+}
 
-    reader
-        .lines()
-        .map(Result::unwrap)
-        .map(get_seat_id)
-        .for_each(|seat| {
-            if seat > max_seat_id {
-                max_seat_id = seat;
-            };
-        });
+fn qn1(seats: &[SeatID]) {
+    let max_seat_id = seats.iter().copied().max().unwrap_or(0);
     println!("{}", max_seat_id);
 }
 
-fn qn2(path: &str) {
-    let file = File::open(path).expect("cannot open file");
-    let reader = BufReader::new(file);
+fn qn2(report: &Report, show_map: bool) {
+    match find_missing_seat(report) {
+        Some(missing_seat) => println!("Missing seat: {}", missing_seat),
+        None => println!("Missing seat: none or ambiguous"),
+    }
+    if show_map {
+        print!("{}", report.render());
+    }
+}
 
-    let mut seats: Vec<SeatID> = reader
+fn simulate(path: &str, cabin: Cabin) {
+    let file = File::open(path).expect("cannot open file");
+    let passengers: Vec<Passenger> = BufReader::new(file)
         .lines()
         .map(Result::unwrap)
-        .map(get_seat_id)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.parse().unwrap_or_else(|e| panic!("{}", e)))
         .collect();
 
-    let missing_seat = find_missing_seat(&mut seats);
-
-    println!("Missing seat: {}", missing_seat);
+    let outcome = Simulator::new(cabin).board(&passengers);
+    for assignment in &outcome.assignments {
+        println!("{} {}", assignment.passenger, assignment.pass);
+    }
+    for (passenger, unmet) in &outcome.unmet {
+        println!("{}: {}", passenger, unmet);
+    }
+    println!(
+        "Utilisation: {}/{} ({:.1}%)",
+        outcome.occupied,
+        outcome.capacity,
+        outcome.utilisation() * 100.0
+    );
 }
 
-fn get_seat_id(seat: String) -> SeatID {
-    let (mut min, mut max) = (0, 127);
-    let (columns, rows) = seat.split_at(7);
-
-    columns.chars().for_each(|col| {
-        match col {
-            'F' => max -= (max - min + 1) / 2,
-            'B' => min += (max - min + 1) / 2,
-            _ => panic!("abort"),
-        };
-    });
-
-    let seat_row = min;
-
-    let (mut min, mut max) = (0, 7);
-
-    rows.chars().for_each(|row| {
-        match row {
-            'L' => max -= (max - min + 1) / 2,
-            'R' => min += (max - min + 1) / 2,
-            _ => panic!("abort"),
-        };
-    });
-
-    let seat_column = min;
-
-    seat_row * 8 + seat_column
+fn get_seat_id(seat: &str, layout: &Layout) -> Result<SeatID, PassError> {
+    Ok(layout.seat_id(layout.decode(seat)?))
 }
 
-// Brute force omg O(n)
-fn find_missing_seat(seats: &mut [SeatID]) -> SeatID {
-    seats.sort_unstable();
-    let (first, last) = (seats[0], seats[seats.len() - 1]);
-
-    for (seat_id, expected_seat_id) in seats.iter().zip(first..last) {
-        if *seat_id != expected_seat_id {
-            return expected_seat_id;
-        }
+// Ours is the only empty seat with both neighbours taken
+fn find_missing_seat(report: &Report) -> Option<SeatID> {
+    match report.between_occupied.as_slice() {
+        [seat] => Some(*seat),
+        _ => None,
     }
-    panic!("Illegal state");
 }
 
 #[cfg(test)]
@@ -90,14 +136,35 @@ mod tests {
 
     #[test]
     fn should_return_correct_missing_seat() {
-        assert_eq!(find_missing_seat(&mut [0, 1, 3]), 2);
+        let layout = Layout::default();
+        let report = |ids: &[SeatID]| Report::new(&layout, ids, vec![]);
+        assert_eq!(find_missing_seat(&report(&[0, 1, 3])), Some(2));
+        assert_eq!(find_missing_seat(&report(&[0, 1, 2])), None);
+        assert_eq!(find_missing_seat(&report(&[0, 2, 4])), None);
     }
 
     #[test]
     fn check_seat_ids() {
-        assert_eq!(get_seat_id("FBFBBFFRLR".into()), 357);
-        assert_eq!(get_seat_id("BFFFBBFRRR".into()), 567);
-        assert_eq!(get_seat_id("FFFBBBFRRR".into()), 119);
-        assert_eq!(get_seat_id("BBFFBBFRLL".into()), 820);
+        assert_eq!(get_seat_id("FBFBBFFRLR", &Layout::default()), Ok(357));
+        assert_eq!(get_seat_id("BFFFBBFRRR", &Layout::default()), Ok(567));
+        assert_eq!(get_seat_id("FFFBBBFRRR", &Layout::default()), Ok(119));
+        assert_eq!(get_seat_id("BBFFBBFRLL", &Layout::default()), Ok(820));
+    }
+
+    // Every seat in a range of cabins encodes to a pass that decodes back
+    #[test]
+    fn encoding_round_trips() {
+        let layouts = ["7,3", "1,1", "4,0", "0,4", "5,2,UD,<>", "10,6,01,ab"];
+        for layout in layouts.iter().map(|s| s.parse::<Layout>().unwrap()) {
+            let seats = 1 << (layout.row_bits + layout.column_bits);
+            for id in 0..seats {
+                let pass = layout.encode_id(id).unwrap();
+                assert_eq!(get_seat_id(&pass, &layout), Ok(id), "{:?} {}", layout, pass);
+
+                let seat = layout.seat(id).unwrap();
+                assert_eq!(layout.encode(seat), Ok(pass));
+            }
+            assert!(layout.encode_id(seats).is_err());
+        }
     }
 }
@@ -0,0 +1,531 @@
+// Boards a stream of passengers onto a cabin, honouring seat preferences
+// where possible. Passengers are written one per line as
+//
+//     <name> [window|aisle|any] [<group>]
+//
+// and passengers sharing a group want to sit side by side in one row.
+
+use crate::layout::{Layout, Seat};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Preference {
+    Window,
+    Aisle,
+    Any,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Passenger {
+    pub name: String,
+    pub preference: Preference,
+    pub group: Option<String>,
+}
+impl FromStr for Passenger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let preference = |s: &str| match s {
+            "window" => Ok(Preference::Window),
+            "aisle" => Ok(Preference::Aisle),
+            "any" => Ok(Preference::Any),
+            _ => Err(format!("bad preference: {}", s)),
+        };
+        let (name, preference, group) = match parts.as_slice() {
+            [name] => (name, Preference::Any, None),
+            [name, pref] => (name, preference(pref)?, None),
+            [name, pref, group] => (name, preference(pref)?, Some(group.to_string())),
+            _ => return Err(format!("bad passenger: {}", s)),
+        };
+        Ok(Passenger {
+            name: name.to_string(),
+            preference,
+            group,
+        })
+    }
+}
+
+// A layout with aisles running between some columns
+pub struct Cabin {
+    pub layout: Layout,
+    // An aisle runs after each of these columns
+    pub aisles: Vec<u32>,
+}
+impl Cabin {
+    pub fn new(layout: Layout, aisles: Vec<u32>) -> Result<Cabin, String> {
+        if let Some(aisle) = aisles.iter().find(|&&a| a + 1 >= layout.columns()) {
+            return Err(format!("no room for an aisle after column {}", aisle));
+        }
+        Ok(Cabin { layout, aisles })
+    }
+
+    // One aisle down the middle, if there is more than one column
+    pub fn with_middle_aisle(layout: Layout) -> Cabin {
+        let aisles = if layout.columns() > 1 {
+            vec![layout.columns() / 2 - 1]
+        } else {
+            vec![]
+        };
+        Cabin { layout, aisles }
+    }
+
+    fn is_window(&self, column: u32) -> bool {
+        column == 0 || column == self.layout.columns() - 1
+    }
+
+    fn is_aisle(&self, column: u32) -> bool {
+        self.aisles.iter().any(|&a| column == a || column == a + 1)
+    }
+
+    fn suits(&self, column: u32, preference: Preference) -> bool {
+        match preference {
+            Preference::Window => self.is_window(column),
+            Preference::Aisle => self.is_aisle(column),
+            Preference::Any => true,
+        }
+    }
+
+    // Window and aisle columns, in order
+    fn special_columns(&self) -> Vec<u32> {
+        let columns = self.layout.columns();
+        let mut special: Vec<u32> = vec![0, columns - 1]
+            .into_iter()
+            .chain(self.aisles.iter().flat_map(|&a| vec![a, a + 1]))
+            .collect();
+        special.sort_unstable();
+        special.dedup();
+        special
+    }
+
+    // Whether `count` seats from `start` sit together without an aisle between
+    fn is_block(&self, start: u32, count: u32) -> bool {
+        !self
+            .aisles
+            .iter()
+            .any(|&a| start <= a && a + 1 < start + count)
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Unmet {
+    Window,
+    Aisle,
+    Group(String),
+    NoSeat,
+}
+impl fmt::Display for Unmet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unmet::Window => write!(f, "wanted a window seat"),
+            Unmet::Aisle => write!(f, "wanted an aisle seat"),
+            Unmet::Group(group) => write!(f, "not seated with group {}", group),
+            Unmet::NoSeat => write!(f, "no seat left"),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Assignment {
+    pub passenger: String,
+    pub seat: Seat,
+    pub pass: String,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Outcome {
+    pub assignments: Vec<Assignment>,
+    pub unmet: Vec<(String, Unmet)>,
+    pub occupied: usize,
+    pub capacity: usize,
+}
+impl Outcome {
+    pub fn utilisation(&self) -> f64 {
+        self.occupied as f64 / self.capacity as f64
+    }
+}
+
+pub struct Simulator {
+    cabin: Cabin,
+    // Taken columns by row; rows nobody sits in yet are left out
+    taken: BTreeMap<u32, BTreeSet<u32>>,
+    special: Vec<u32>,
+    // For each preference, the first row that may still have a free seat
+    // suiting it. Seats are never given back, so these only move forward.
+    cursors: [u32; 3],
+    outcome: Outcome,
+}
+impl Simulator {
+    pub fn new(cabin: Cabin) -> Simulator {
+        let (rows, columns) = (cabin.layout.rows(), cabin.layout.columns());
+        Simulator {
+            taken: BTreeMap::new(),
+            special: cabin.special_columns(),
+            cursors: [0; 3],
+            outcome: Outcome {
+                assignments: vec![],
+                unmet: vec![],
+                occupied: 0,
+                capacity: (rows * columns) as usize,
+            },
+            cabin,
+        }
+    }
+
+    // Seats passengers in order; a group boards when its first member does
+    pub fn board(mut self, passengers: &[Passenger]) -> Outcome {
+        let mut boarded: HashSet<&str> = HashSet::new();
+        for passenger in passengers {
+            match &passenger.group {
+                Some(group) if boarded.insert(group) => {
+                    let members: Vec<&Passenger> = passengers
+                        .iter()
+                        .filter(|p| p.group.as_ref() == Some(group))
+                        .collect();
+                    self.seat_group(group, &members);
+                }
+                Some(_) => {}
+                None => {
+                    self.seat_one(passenger);
+                }
+            }
+        }
+        self.outcome
+    }
+
+    fn take(&mut self, passenger: &Passenger, seat: Seat) {
+        self.taken.entry(seat.row).or_default().insert(seat.column);
+        let pass = self.cabin.layout.encode(seat).expect("seat in cabin");
+        self.outcome.assignments.push(Assignment {
+            passenger: passenger.name.clone(),
+            seat,
+            pass,
+        });
+        self.outcome.occupied += 1;
+
+        let unmet = match passenger.preference {
+            _ if self.cabin.suits(seat.column, passenger.preference) => return,
+            Preference::Window => Unmet::Window,
+            Preference::Aisle => Unmet::Aisle,
+            Preference::Any => return,
+        };
+        self.outcome.unmet.push((passenger.name.clone(), unmet));
+    }
+
+    fn num_free(&self, row: u32) -> u32 {
+        let taken = self.taken.get(&row).map_or(0, |taken| taken.len());
+        self.cabin.layout.columns() - taken as u32
+    }
+
+    fn is_free(&self, row: u32, start: u32, count: u32) -> bool {
+        self.taken
+            .get(&row)
+            .is_none_or(|taken| taken.range(start..start + count).next().is_none())
+    }
+
+    // The first free seat that suits, in row order, moving the cursor for
+    // the preference up to its row
+    fn next_free(&mut self, preference: Preference) -> Option<Seat> {
+        let mut row = self.cursors[preference as usize];
+        let mut found = None;
+        while row < self.cabin.layout.rows() && found.is_none() {
+            let taken = self.taken.get(&row);
+            let is_free = |column: &u32| taken.is_none_or(|taken| !taken.contains(column));
+            let column = match preference {
+                // Stops at the first gap, so costs no more than the seats taken
+                Preference::Any => (0..self.cabin.layout.columns()).find(is_free),
+                _ => self
+                    .special
+                    .iter()
+                    .copied()
+                    .filter(|&c| self.cabin.suits(c, preference))
+                    .find(is_free),
+            };
+            match column {
+                Some(column) => found = Some(Seat { row, column }),
+                None => row += 1,
+            }
+        }
+        self.cursors[preference as usize] = row;
+        found
+    }
+
+    // The first free seat that suits, otherwise the first free seat. False
+    // if the cabin is full.
+    fn seat_one(&mut self, passenger: &Passenger) -> bool {
+        let seat = match self.next_free(passenger.preference) {
+            Some(seat) => Some(seat),
+            None => self.next_free(Preference::Any),
+        };
+        match seat {
+            Some(seat) => {
+                self.take(passenger, seat);
+                true
+            }
+            None => {
+                self.outcome
+                    .unmet
+                    .push((passenger.name.clone(), Unmet::NoSeat));
+                false
+            }
+        }
+    }
+
+    // Where blocks of `count` free seats in a row worth trying start: every
+    // block with a window or aisle seat, and the first with neither. Blocks
+    // with neither all meet the same preferences, so after the first one
+    // tried in any row, no later one can do better.
+    fn starts(&self, row: u32, count: u32, plain_tried: &mut bool) -> Vec<u32> {
+        let columns = self.cabin.layout.columns();
+        if count > columns {
+            return vec![];
+        }
+        let last = columns - count;
+        let mut starts: Vec<u32> = self
+            .special
+            .iter()
+            .flat_map(|&c| c.saturating_sub(count - 1)..=c.min(last))
+            .collect();
+
+        if !*plain_tried {
+            let taken = self.taken.get(&row);
+            let mut start = 0;
+            while start <= last {
+                let end = start + count;
+                let taken = taken.and_then(|taken| taken.range(start..end).next_back().copied());
+                let special = self.special.iter().rev().find(|&&c| start <= c && c < end);
+                // Jump past whatever is in the way
+                match taken.max(special.copied()) {
+                    Some(column) => start = column + 1,
+                    None => {
+                        starts.push(start);
+                        *plain_tried = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        starts.sort_unstable();
+        starts.dedup();
+        starts
+            .retain(|&start| self.cabin.is_block(start, count) && self.is_free(row, start, count));
+        starts
+    }
+
+    // Which column of a block each member gets, window and aisle seats going
+    // to those who asked first, and how many preferences that meets
+    fn arrange(&self, members: &[&Passenger], columns: &[u32]) -> (Vec<u32>, usize) {
+        let mut free = columns.to_vec();
+        let mut placed: Vec<Option<u32>> = vec![None; members.len()];
+        for &preference in &[Preference::Window, Preference::Aisle] {
+            for (i, member) in members.iter().enumerate() {
+                if member.preference != preference {
+                    continue;
+                }
+                if let Some(j) = free.iter().position(|&c| self.cabin.suits(c, preference)) {
+                    placed[i] = Some(free.remove(j));
+                }
+            }
+        }
+        let met = members
+            .iter()
+            .zip(&placed)
+            .filter(|(member, column)| column.is_some() || member.preference == Preference::Any)
+            .count();
+        let mut rest = free.into_iter();
+        let placed = placed
+            .into_iter()
+            .map(|column| column.or_else(|| rest.next()).unwrap())
+            .collect();
+        (placed, met)
+    }
+
+    fn seat_group(&mut self, group: &str, members: &[&Passenger]) {
+        let count = members.len() as u32;
+        let mut best: Option<(usize, u32, Vec<u32>)> = None;
+
+        // Rows nobody sits in all look alike, so only the first is tried
+        let untouched = (0..self.cabin.layout.rows()).find(|row| !self.taken.contains_key(row));
+        let mut rows: Vec<u32> = self.taken.keys().copied().chain(untouched).collect();
+        rows.sort_unstable();
+
+        let mut plain_tried = false;
+        'rows: for row in rows {
+            if self.num_free(row) < count {
+                continue;
+            }
+            for start in self.starts(row, count, &mut plain_tried) {
+                let columns: Vec<u32> = (start..start + count).collect();
+                let (placed, met) = self.arrange(members, &columns);
+                if best.as_ref().is_none_or(|(best_met, _, _)| met > *best_met) {
+                    best = Some((met, row, placed));
+                }
+                if met == members.len() {
+                    break 'rows;
+                }
+            }
+        }
+
+        match best {
+            Some((_, row, placed)) => {
+                for (member, column) in members.iter().zip(placed) {
+                    self.take(member, Seat { row, column });
+                }
+            }
+            None => {
+                for member in members {
+                    // Passengers left without a seat are only short a seat
+                    if self.seat_one(member) && count > 1 {
+                        let unmet = Unmet::Group(group.to_string());
+                        self.outcome.unmet.push((member.name.clone(), unmet));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passengers(lines: &str) -> Vec<Passenger> {
+        lines.lines().map(|line| line.parse().unwrap()).collect()
+    }
+
+    fn board(layout: &str, aisles: Vec<u32>, lines: &str) -> Outcome {
+        let cabin = Cabin::new(layout.parse().unwrap(), aisles).unwrap();
+        Simulator::new(cabin).board(&passengers(lines))
+    }
+
+    #[test]
+    fn parsing_passengers() {
+        assert_eq!(
+            "ann window g1".parse(),
+            Ok(Passenger {
+                name: "ann".into(),
+                preference: Preference::Window,
+                group: Some("g1".into())
+            })
+        );
+        assert_eq!(
+            "bob".parse::<Passenger>().unwrap().preference,
+            Preference::Any
+        );
+        assert!("bob middle".parse::<Passenger>().is_err());
+        assert!("".parse::<Passenger>().is_err());
+    }
+
+    #[test]
+    fn preferences() {
+        // 2 rows of 4 seats, aisle down the middle
+        let outcome = board("1,2", vec![1], "ann window\nbob aisle\ncat window\ndan");
+        let seats: Vec<(u32, u32)> = outcome
+            .assignments
+            .iter()
+            .map(|a| (a.seat.row, a.seat.column))
+            .collect();
+        assert_eq!(seats, vec![(0, 0), (0, 1), (0, 3), (0, 2)]);
+        assert!(outcome.unmet.is_empty());
+        assert_eq!(outcome.utilisation(), 0.5);
+    }
+
+    #[test]
+    fn groups_sit_together() {
+        let outcome = board(
+            "1,2",
+            vec![1],
+            "ann\nbob aisle family\ncat window family\ndan any family",
+        );
+        // The family cannot fit on either side of the aisle, so is split
+        // up row by row
+        let unmet: Vec<String> = outcome
+            .unmet
+            .iter()
+            .map(|(name, unmet)| format!("{} {}", name, unmet))
+            .collect();
+        assert_eq!(
+            unmet,
+            vec![
+                "bob not seated with group family",
+                "cat not seated with group family",
+                "dan not seated with group family",
+            ]
+        );
+
+        let outcome = board("2,2", vec![1], "ann\nbob aisle pair\ncat window pair");
+        let seats: Vec<(u32, u32)> = outcome
+            .assignments
+            .iter()
+            .map(|a| (a.seat.row, a.seat.column))
+            .collect();
+        assert_eq!(seats, vec![(0, 0), (0, 2), (0, 3)]);
+        assert!(outcome.unmet.is_empty());
+    }
+
+    #[test]
+    fn full_cabin() {
+        let outcome = board("1,0", vec![], "ann\nbob\ncat aisle");
+        assert_eq!(outcome.assignments.len(), 2);
+        assert_eq!(outcome.unmet, vec![("cat".to_string(), Unmet::NoSeat)]);
+        assert_eq!(outcome.utilisation(), 1.0);
+
+        // Of a group split up by a full cabin, only those seated miss the group
+        let outcome = board(
+            "1,0",
+            vec![],
+            "ann\nbob any trio\ncat any trio\ndan any trio",
+        );
+        assert_eq!(
+            outcome.unmet,
+            vec![
+                ("bob".to_string(), Unmet::Group("trio".into())),
+                ("cat".to_string(), Unmet::NoSeat),
+                ("dan".to_string(), Unmet::NoSeat),
+            ]
+        );
+    }
+
+    // Only the seats taken are stored, so the largest cabin boards quickly
+    #[test]
+    fn largest_cabin() {
+        let layout: Layout = "15,15".parse().unwrap();
+        let lines = "ann window\nbob aisle trio\ncat window trio\ndan any trio\neve window";
+        let outcome = Simulator::new(Cabin::with_middle_aisle(layout)).board(&passengers(lines));
+        let seats: Vec<(u32, u32)> = outcome
+            .assignments
+            .iter()
+            .map(|a| (a.seat.row, a.seat.column))
+            .collect();
+        assert_eq!(
+            seats,
+            vec![(0, 0), (0, 16383), (0, 16381), (0, 16382), (0, 32767)]
+        );
+        assert_eq!(outcome.unmet, vec![("cat".to_string(), Unmet::Window)]);
+        assert_eq!(outcome.capacity, 1 << 30);
+    }
+
+    // Every pass handed out decodes back to its seat
+    #[test]
+    fn passes_round_trip() {
+        let layout = Layout::default();
+        let lines: Vec<String> = (0..1100)
+            .map(|i| {
+                let preference = ["window", "aisle", "any"][i % 3];
+                format!("p{} {} g{}", i, preference, i / 3)
+            })
+            .collect();
+        let outcome =
+            Simulator::new(Cabin::with_middle_aisle(layout)).board(&passengers(&lines.join("\n")));
+
+        assert_eq!(outcome.occupied, 1024);
+        let mut seen = HashSet::new();
+        for assignment in &outcome.assignments {
+            assert_eq!(layout.decode(&assignment.pass), Ok(assignment.seat));
+            assert!(seen.insert(assignment.pass.clone()));
+        }
+    }
+}
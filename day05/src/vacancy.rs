@@ -0,0 +1,172 @@
+use crate::layout::{Layout, Seat, SeatID};
+use std::collections::BTreeMap;
+
+// Which seats of a cabin a batch of boarding passes fills. Only taken seats
+// are stored, so large cabins cost no more than small ones.
+#[derive(PartialEq, Debug)]
+pub struct Report {
+    pub layout: Layout,
+    // Taken seats, and how many passes each has
+    pub passes: BTreeMap<SeatID, usize>,
+    // Runs of empty seats as (first, last) IDs, including any at the front
+    // and back of the cabin
+    pub empty: Vec<(SeatID, SeatID)>,
+    // Empty seats whose IDs either side are both taken
+    pub between_occupied: Vec<SeatID>,
+    // Seats with more than one pass, and how many
+    pub duplicates: Vec<(SeatID, usize)>,
+    // Passes too long or too short for the layout, with their line numbers
+    pub out_of_range: Vec<(usize, String)>,
+    // Seat IDs past the last seat of the cabin, in the order given
+    pub out_of_range_ids: Vec<SeatID>,
+}
+impl Report {
+    pub fn new(layout: &Layout, ids: &[SeatID], out_of_range: Vec<(usize, String)>) -> Report {
+        let mut passes: BTreeMap<SeatID, usize> = BTreeMap::new();
+        let mut out_of_range_ids = vec![];
+        for &id in ids {
+            if layout.seat(id).is_some() {
+                *passes.entry(id).or_default() += 1;
+            } else {
+                out_of_range_ids.push(id);
+            }
+        }
+
+        let capacity = (layout.rows() * layout.columns()) as SeatID;
+        let mut empty = vec![];
+        let mut next = 0;
+        for &id in passes.keys() {
+            if id > next {
+                empty.push((next, id - 1));
+            }
+            next = id + 1;
+        }
+        if next < capacity {
+            empty.push((next, capacity - 1));
+        }
+
+        // Runs are as long as they can be, so a single seat run away from
+        // the ends has a taken seat either side
+        let between_occupied = empty
+            .iter()
+            .filter(|&&(first, last)| first == last && first > 0 && last < capacity - 1)
+            .map(|&(id, _)| id)
+            .collect();
+        let duplicates = passes
+            .iter()
+            .filter(|&(_, &count)| count > 1)
+            .map(|(&id, &count)| (id, count))
+            .collect();
+
+        Report {
+            layout: *layout,
+            passes,
+            empty,
+            between_occupied,
+            duplicates,
+            out_of_range,
+            out_of_range_ids,
+        }
+    }
+
+    pub fn is_occupied(&self, id: SeatID) -> bool {
+        self.passes.contains_key(&id)
+    }
+
+    pub fn num_empty(&self) -> usize {
+        self.empty
+            .iter()
+            .map(|&(first, last)| (last - first + 1) as usize)
+            .sum()
+    }
+
+    // One line per row: '#' taken, '.' empty, '?' empty between two taken
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for row in 0..self.layout.rows() {
+            out.push_str(&format!("{:>4} ", row));
+            for column in 0..self.layout.columns() {
+                let id = self.layout.seat_id(Seat { row, column });
+                out.push(if self.is_occupied(id) {
+                    '#'
+                } else if self.between_occupied.binary_search(&id).is_ok() {
+                    '?'
+                } else {
+                    '.'
+                });
+            }
+            out.push('\n');
+        }
+
+        out.push_str(&format!("{} empty seats\n", self.num_empty()));
+        for id in &self.between_occupied {
+            out.push_str(&format!("empty between two taken seats: {}\n", id));
+        }
+        for (id, count) in &self.duplicates {
+            out.push_str(&format!("{} passes for seat {}\n", count, id));
+        }
+        for (line, pass) in &self.out_of_range {
+            out.push_str(&format!("out of range: line {}, {}\n", line, pass));
+        }
+        for id in &self.out_of_range_ids {
+            out.push_str(&format!("out of range: seat ID {}\n", id));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_cabin() {
+        let layout: Layout = "2,1".parse().unwrap();
+        let report = Report::new(&layout, &[1, 2, 2, 4, 6, 8], vec![(6, "FFLR".into())]);
+
+        assert_eq!(
+            report.passes.keys().copied().collect::<Vec<_>>(),
+            vec![1, 2, 4, 6]
+        );
+        assert_eq!(report.empty, vec![(0, 0), (3, 3), (5, 5), (7, 7)]);
+        assert_eq!(report.num_empty(), 4);
+        assert_eq!(report.between_occupied, vec![3, 5]);
+        assert_eq!(report.duplicates, vec![(2, 2)]);
+        assert_eq!(report.out_of_range_ids, vec![8]);
+        assert_eq!(
+            report.render(),
+            "   0 .#\n   1 #?\n   2 #?\n   3 #.\n\
+             4 empty seats\n\
+             empty between two taken seats: 3\n\
+             empty between two taken seats: 5\n\
+             2 passes for seat 2\n\
+             out of range: line 6, FFLR\n\
+             out of range: seat ID 8\n"
+        );
+    }
+
+    #[test]
+    fn full_and_empty_cabins() {
+        let layout: Layout = "1,1".parse().unwrap();
+        let full = Report::new(&layout, &[0, 1, 2, 3], vec![]);
+        assert!(full.empty.is_empty());
+        assert!(full.between_occupied.is_empty());
+        assert!(full.out_of_range_ids.is_empty());
+
+        let empty = Report::new(&layout, &[], vec![]);
+        assert_eq!(empty.empty, vec![(0, 3)]);
+        assert_eq!(empty.num_empty(), 4);
+        assert!(empty.between_occupied.is_empty());
+    }
+
+    #[test]
+    fn largest_cabin() {
+        let layout: Layout = "15,15".parse().unwrap();
+        let last = (1 << 30) - 1;
+        let report = Report::new(&layout, &[0, 5, 7, last], vec![]);
+
+        assert_eq!(report.empty, vec![(1, 4), (6, 6), (8, last - 1)]);
+        assert_eq!(report.num_empty(), (1 << 30) - 4);
+        assert_eq!(report.between_occupied, vec![6]);
+    }
+}
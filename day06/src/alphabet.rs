@@ -0,0 +1,197 @@
+use crate::answers::Answers;
+use std::fmt;
+use std::str::FromStr;
+
+// Answers are stored as u64 masks
+pub const MAX_QUESTIONS: usize = 64;
+
+// The questions that can be answered, in order. Written as the questions
+// themselves, with "x-y" standing for every character from x to y.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Alphabet {
+    questions: Vec<char>,
+}
+impl Default for Alphabet {
+    fn default() -> Self {
+        Alphabet {
+            questions: ('a'..='z').collect(),
+        }
+    }
+}
+impl FromStr for Alphabet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = s.chars().collect();
+        let mut questions: Vec<char> = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i..] {
+                [lo, '-', hi, ..] => {
+                    if lo > hi {
+                        return Err(format!("bad range {}-{}", lo, hi));
+                    }
+                    questions.extend(lo..=hi);
+                    i += 3;
+                }
+                [c, ..] => {
+                    questions.push(c);
+                    i += 1;
+                }
+                [] => unreachable!(),
+            }
+        }
+
+        if questions.is_empty() {
+            return Err("empty alphabet".to_string());
+        }
+        if questions.len() > MAX_QUESTIONS {
+            return Err(format!(
+                "{} questions, at most {} allowed",
+                questions.len(),
+                MAX_QUESTIONS
+            ));
+        }
+        for (i, question) in questions.iter().enumerate() {
+            if questions[..i].contains(question) {
+                return Err(format!("{:?} appears twice", question));
+            }
+        }
+        Ok(Alphabet { questions })
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum AnswerError {
+    // Line and column start at 1
+    Unknown {
+        line: usize,
+        column: usize,
+        answer: char,
+    },
+    Duplicate {
+        line: usize,
+        column: usize,
+        answer: char,
+    },
+}
+impl fmt::Display for AnswerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnswerError::Unknown {
+                line,
+                column,
+                answer,
+            } => write!(
+                f,
+                "line {}, column {}: {:?} is not a question",
+                line, column, answer
+            ),
+            AnswerError::Duplicate {
+                line,
+                column,
+                answer,
+            } => write!(
+                f,
+                "line {}, column {}: {:?} answered twice",
+                line, column, answer
+            ),
+        }
+    }
+}
+
+impl Alphabet {
+    pub fn questions(&self) -> &[char] {
+        &self.questions
+    }
+
+    pub fn all(&self) -> Answers {
+        Answers(u64::MAX >> (MAX_QUESTIONS - self.questions.len()))
+    }
+
+    pub fn index(&self, question: char) -> Option<usize> {
+        self.questions.iter().position(|&q| q == question)
+    }
+
+    // One person's answers from line `line`, skipping and reporting anything
+    // that is not a question or was already answered
+    pub fn parse(&self, answers: &str, line: usize) -> (Answers, Vec<AnswerError>) {
+        let mut mask = Answers::default();
+        let mut errors = vec![];
+        for (i, answer) in answers.chars().enumerate() {
+            let column = i + 1;
+            match self.index(answer) {
+                Some(index) if mask.contains(index) => errors.push(AnswerError::Duplicate {
+                    line,
+                    column,
+                    answer,
+                }),
+                Some(index) => mask = mask | Answers(1 << index),
+                None => errors.push(AnswerError::Unknown {
+                    line,
+                    column,
+                    answer,
+                }),
+            }
+        }
+        (mask, errors)
+    }
+
+    #[cfg(test)]
+    pub fn format(&self, answers: Answers) -> String {
+        answers.indexes().map(|i| self.questions[i]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_alphabets() {
+        let alphabet: Alphabet = "a-z".parse().unwrap();
+        assert_eq!(alphabet, Alphabet::default());
+        assert_eq!(alphabet.all().len(), 26);
+
+        let greek: Alphabet = "α-ω".parse().unwrap();
+        assert_eq!(greek.questions().len(), 25);
+        assert_eq!(greek.index('β'), Some(1));
+
+        let custom: Alphabet = "xy0-2-".parse().unwrap();
+        assert_eq!(custom.questions(), &['x', 'y', '0', '1', '2', '-']);
+
+        let wide: Alphabet = "\u{100}-\u{13f}".parse().unwrap();
+        assert_eq!(wide.all(), Answers(u64::MAX));
+
+        assert!("".parse::<Alphabet>().is_err());
+        assert!("z-a".parse::<Alphabet>().is_err());
+        assert!("aba".parse::<Alphabet>().is_err());
+        assert!("\u{100}-\u{140}".parse::<Alphabet>().is_err());
+    }
+
+    #[test]
+    fn parse_answers() {
+        let alphabet = Alphabet::default();
+        let (answers, errors) = alphabet.parse("abc", 1);
+        assert_eq!(alphabet.format(answers), "abc");
+        assert!(errors.is_empty());
+
+        let (answers, errors) = alphabet.parse("aB c a", 7);
+        assert_eq!(alphabet.format(answers), "ac");
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line 7, column 2: 'B' is not a question",
+                "line 7, column 3: ' ' is not a question",
+                "line 7, column 5: ' ' is not a question",
+                "line 7, column 6: 'a' answered twice",
+            ]
+        );
+
+        let greek: Alphabet = "αβγ".parse().unwrap();
+        let (answers, errors) = greek.parse("γα", 1);
+        assert_eq!(greek.format(answers), "αγ");
+        assert!(errors.is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use std::ops::{BitAnd, BitOr, BitXor};
+use std::str::FromStr;
+
+// The questions one or more people answered "yes" to, bit i being question
+// i of the alphabet
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Answers(pub u64);
+impl Answers {
+    pub fn contains(&self, index: usize) -> bool {
+        self.0 >> index & 1 == 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn indexes(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..64).filter(move |&index| self.contains(index))
+    }
+}
+impl BitOr for Answers {
+    type Output = Answers;
+    fn bitor(self, rhs: Answers) -> Answers {
+        Answers(self.0 | rhs.0)
+    }
+}
+impl BitAnd for Answers {
+    type Output = Answers;
+    fn bitand(self, rhs: Answers) -> Answers {
+        Answers(self.0 & rhs.0)
+    }
+}
+impl BitXor for Answers {
+    type Output = Answers;
+    fn bitxor(self, rhs: Answers) -> Answers {
+        Answers(self.0 ^ rhs.0)
+    }
+}
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Query {
+    // Answered by anyone
+    Union,
+    // Answered by everyone
+    Intersection,
+    // Answered by an odd number of people
+    SymmetricDifference,
+    Exactly(usize),
+    AtLeast(usize),
+}
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let count = |k: &str| k.parse().map_err(|_| format!("bad count: {}", k));
+        match s.split_once(':') {
+            None if s == "union" => Ok(Query::Union),
+            None if s == "intersection" => Ok(Query::Intersection),
+            None if s == "xor" => Ok(Query::SymmetricDifference),
+            Some(("exactly", k)) => Ok(Query::Exactly(count(k)?)),
+            Some(("at-least", k)) => Ok(Query::AtLeast(count(k)?)),
+            _ => Err(format!("bad query: {}", s)),
+        }
+    }
+}
+
+// One group's answers, a mask per person
+#[derive(Clone, PartialEq, Debug)]
+pub struct Group {
+    // Every question that could be answered
+    pub questions: Answers,
+    pub people: Vec<Answers>,
+}
+impl Group {
+    pub fn new(questions: Answers) -> Group {
+        Group {
+            questions,
+            people: vec![],
+        }
+    }
+
+    pub fn push(&mut self, answers: Answers) {
+        self.people.push(answers);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.people.is_empty()
+    }
+
+    pub fn union(&self) -> Answers {
+        self.people
+            .iter()
+            .fold(Answers::default(), |acc, &a| acc | a)
+    }
+
+    pub fn intersection(&self) -> Answers {
+        if self.is_empty() {
+            return Answers::default();
+        }
+        self.people.iter().fold(self.questions, |acc, &a| acc & a)
+    }
+
+    pub fn symmetric_difference(&self) -> Answers {
+        self.people
+            .iter()
+            .fold(Answers::default(), |acc, &a| acc ^ a)
+    }
+
+    // Questions answered by exactly and by at least k people, counted with
+    // bitwise adders: bit i of counts[j] is bit j of question i's count
+    pub fn exactly(&self, k: usize) -> Answers {
+        let counts = self.counts();
+        if k >> counts.len() != 0 {
+            return Answers::default();
+        }
+        let mask = counts
+            .iter()
+            .enumerate()
+            .fold(self.questions.0, |mask, (j, &bits)| {
+                mask & if k >> j & 1 == 1 { bits } else { !bits }
+            });
+        Answers(mask & self.questions.0)
+    }
+
+    pub fn at_least(&self, k: usize) -> Answers {
+        (k..=self.people.len())
+            .map(|n| self.exactly(n))
+            .fold(Answers::default(), |acc, a| acc | a)
+    }
+
+    fn counts(&self) -> Vec<u64> {
+        let mut counts: Vec<u64> = vec![];
+        for person in &self.people {
+            let mut carry = person.0;
+            for bits in counts.iter_mut() {
+                let sum = *bits ^ carry;
+                carry &= *bits;
+                *bits = sum;
+            }
+            if carry != 0 {
+                counts.push(carry);
+            }
+        }
+        counts
+    }
+
+    pub fn answers(&self, query: Query) -> Answers {
+        match query {
+            Query::Union => self.union(),
+            Query::Intersection => self.intersection(),
+            Query::SymmetricDifference => self.symmetric_difference(),
+            Query::Exactly(k) => self.exactly(k),
+            Query::AtLeast(k) => self.at_least(k),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alphabet::Alphabet;
+
+    fn group(people: &[&str]) -> Group {
+        let alphabet = Alphabet::default();
+        Group {
+            questions: alphabet.all(),
+            people: people.iter().map(|p| alphabet.parse(p, 1).0).collect(),
+        }
+    }
+
+    fn format(answers: Answers) -> String {
+        Alphabet::default().format(answers)
+    }
+
+    #[test]
+    fn masks() {
+        let answers = Answers(0b1011);
+        assert_eq!(answers.len(), 3);
+        assert!(answers.contains(3));
+        assert!(!answers.contains(2));
+        assert_eq!(answers.indexes().collect::<Vec<_>>(), vec![0, 1, 3]);
+        assert_eq!(format(answers), "abd");
+    }
+
+    #[test]
+    fn group_algebra() {
+        let g = group(&["abc", "abd", "ae", "a"]);
+        assert_eq!(format(g.union()), "abcde");
+        assert_eq!(format(g.intersection()), "a");
+        assert_eq!(format(g.symmetric_difference()), "cde");
+        assert_eq!(format(g.exactly(1)), "cde");
+        assert_eq!(format(g.exactly(2)), "b");
+        assert_eq!(format(g.exactly(4)), "a");
+        assert_eq!(format(g.exactly(0)), "fghijklmnopqrstuvwxyz");
+        assert_eq!(g.exactly(9), Answers::default());
+        assert_eq!(format(g.at_least(2)), "ab");
+    }
+
+    // Union and intersection are the cases k = 1 and k = everyone
+    #[test]
+    fn special_cases() {
+        let groups = [
+            group(&["abc"]),
+            group(&["a", "b", "c"]),
+            group(&["ab", "ac"]),
+            group(&["a", "a", "a", "a"]),
+            group(&["b"]),
+            group(&[]),
+        ];
+        for g in &groups {
+            assert_eq!(g.union(), g.at_least(1));
+            if !g.is_empty() {
+                assert_eq!(g.intersection(), g.at_least(g.people.len()));
+            }
+        }
+        let total = |query| groups.iter().map(|g| g.answers(query).len()).sum::<usize>();
+        assert_eq!(total(Query::Union), 11);
+        assert_eq!(total(Query::Intersection), 6);
+    }
+
+    #[test]
+    fn queries() {
+        assert_eq!("union".parse(), Ok(Query::Union));
+        assert_eq!("exactly:2".parse(), Ok(Query::Exactly(2)));
+        assert_eq!("at-least:3".parse(), Ok(Query::AtLeast(3)));
+        assert!("at-least:x".parse::<Query>().is_err());
+        assert!("most".parse::<Query>().is_err());
+    }
+}
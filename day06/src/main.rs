@@ -1,105 +1,101 @@
-use std::collections::{HashMap, HashSet};
+use alphabet::{Alphabet, AnswerError};
+use answers::{Group, Query};
+use stats::{Format, Stats};
+use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::mem;
 
-type Count = usize;
-
-struct GlobalCounter {
-    buffer: HashSet<char>,
-    count: Count,
-}
+mod alphabet;
+mod answers;
+mod stats;
 
-impl GlobalCounter {
-    fn add(&mut self, qns: String) {
-        qns.chars().for_each(|qn| {
-            self.buffer.insert(qn);
-        });
-    }
-    fn reset(&mut self) {
-        self.count += self.buffer.len();
-        self.buffer.clear();
-    }
-    fn new() -> GlobalCounter {
-        GlobalCounter {
-            buffer: HashSet::new(),
-            count: 0,
-        }
-    }
-}
+type Count = usize;
 
+// Collects one group's answers at a time
 struct FamilyCounter {
-    data: HashMap<char, Count>,
-    family_size: Count,
+    alphabet: Alphabet,
+    group: Group,
+    errors: Vec<AnswerError>,
 }
 
 impl FamilyCounter {
-    fn add(&mut self, qns: String) {
-        qns.chars().for_each(|qn| {
-            let counter = self.data.entry(qn).or_insert(0);
-            *counter += 1;
-        });
-        self.family_size += 1;
+    fn add(&mut self, qns: String, line: usize) {
+        let (answers, errors) = self.alphabet.parse(&qns, line);
+        self.group.push(answers);
+        self.errors.extend(errors);
     }
-    fn reset(&mut self) {
-        self.data.clear();
-        self.family_size = 0;
+    fn reset(&mut self) -> Group {
+        mem::replace(&mut self.group, Group::new(self.alphabet.all()))
     }
-    fn get_count(&self) -> Count {
-        self.data
-            .values()
-            .filter(|&&count| count == self.family_size)
-            .count()
-    }
-    fn new() -> FamilyCounter {
+    fn new(alphabet: &Alphabet) -> FamilyCounter {
         FamilyCounter {
-            data: HashMap::new(),
-            family_size: 0,
+            alphabet: alphabet.clone(),
+            group: Group::new(alphabet.all()),
+            errors: vec![],
         }
     }
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let alphabet: Alphabet = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--alphabet="))
+        .map(|alphabet| alphabet.parse().unwrap_or_else(|e| panic!("{}", e)))
+        .unwrap_or_default();
+
     let path = "./src/input.txt";
-    part1(path);
-    part2(path);
+    let groups = read_groups(path, &alphabet);
+    println!("Part 1: {}", count(&groups, Query::Union));
+    println!("Part 2: {}", count(&groups, Query::Intersection));
+
+    // --query=union|intersection|xor|exactly:K|at-least:K
+    for arg in &args {
+        if let Some(query) = arg.strip_prefix("--query=") {
+            let query = query.parse().unwrap_or_else(|e| panic!("{}", e));
+            println!("{:?}: {}", query, count(&groups, query));
+        }
+        let format = match arg.strip_prefix("--stats=") {
+            Some("csv") => Format::Csv,
+            Some("json") => Format::Json,
+            Some(other) => panic!("unknown stats format {}", other),
+            None => continue,
+        };
+        print!("{}", Stats::new(&groups, &alphabet).export(format));
+    }
 }
 
-fn part1(path: &str) {
+// Reports answers outside the alphabet and repeated answers, then skips them
+fn read_groups(path: &str, alphabet: &Alphabet) -> Vec<Group> {
     let file = File::open(path).expect("cannot find file");
     let reader = BufReader::new(file);
 
-    let mut counter = GlobalCounter::new();
+    let mut groups = vec![];
+    let mut counter = FamilyCounter::new(alphabet);
     reader
         .lines()
         .map(Result::unwrap)
-        .for_each(|line| match line.as_str() {
-            "" => counter.reset(),
-            _ => counter.add(line),
+        .enumerate()
+        .for_each(|(i, line)| match line.as_str() {
+            "" => groups.push(counter.reset()),
+            _ => counter.add(line, i + 1),
         });
-    counter.reset();
+    groups.push(counter.reset());
+    groups.retain(|group| !group.is_empty());
 
-    println!("Part 1: {}", counter.count);
+    for error in &counter.errors {
+        eprintln!("{}", error);
+    }
+    if !counter.errors.is_empty() {
+        eprintln!("{} bad answers skipped", counter.errors.len());
+    }
+    groups
 }
 
-fn part2(path: &str) {
-    let file = File::open(path).expect("cannot find file");
-    let reader = BufReader::new(file);
-
-    let mut count = 0;
-    let mut counter = FamilyCounter::new();
-    reader
-        .lines()
-        .map(Result::unwrap)
-        .for_each(|line| match line.as_str() {
-            "" => {
-                count += counter.get_count();
-                counter.reset()
-            }
-            _ => counter.add(line),
-        });
-    count += counter.get_count();
-
-    println!("Part 2: {}", count);
+// Questions matching the query, summed over groups
+fn count(groups: &[Group], query: Query) -> Count {
+    groups.iter().map(|group| group.answers(query).len()).sum()
 }
 
 #[cfg(test)]
@@ -108,27 +104,35 @@ mod tests {
 
     #[test]
     fn check_counter() {
-        let mut g = GlobalCounter::new();
-        g.add("sdffv".to_owned());
-
-        assert!(g.buffer.contains(&'s'));
-        assert!(g.buffer.contains(&'d'));
-        assert!(g.buffer.contains(&'f'));
-        assert!(g.buffer.contains(&'v'));
-        assert!(g.count == 0);
-
-        g.reset();
-
-        assert!(g.count == 4);
+        let alphabet = Alphabet::default();
+        let mut g = FamilyCounter::new(&alphabet);
+        g.add("sdffv".to_owned(), 1);
+
+        let union = alphabet.format(g.group.union());
+        assert!(union.contains('s'));
+        assert!(union.contains('d'));
+        assert!(union.contains('f'));
+        assert!(union.contains('v'));
+
+        let groups = vec![g.reset()];
+        assert!(g.group.is_empty());
+        assert!(count(&groups, Query::Union) == 4);
     }
 
     #[test]
     fn check_family_counter() {
-        let mut g = FamilyCounter::new();
-        g.add("sdffv".to_owned());
-        assert!(g.data[&'s'] == 1);
-        assert!(g.data[&'d'] == 1);
-        assert!(g.data[&'f'] == 2);
-        assert!(g.data[&'v'] == 1);
+        let alphabet = Alphabet::default();
+        let mut g = FamilyCounter::new(&alphabet);
+        g.add("sdffv".to_owned(), 1);
+        g.add("sf".to_owned(), 2);
+        let once = alphabet.format(g.group.exactly(1));
+        let twice = alphabet.format(g.group.exactly(2));
+        assert!(twice.contains('s'));
+        assert!(once.contains('d'));
+        // One person answering twice still counts once
+        assert!(twice.contains('f'));
+        assert!(once.contains('v'));
+        assert!(alphabet.format(g.group.intersection()) == twice);
+        assert_eq!(g.errors.len(), 1);
     }
 }
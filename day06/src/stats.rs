@@ -0,0 +1,235 @@
+use crate::alphabet::Alphabet;
+use crate::answers::Group;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct QuestionStats {
+    pub groups: usize,
+    pub people: usize,
+    // Groups where everyone answered, and the people in them
+    pub unanimous_groups: usize,
+    pub unanimous_people: usize,
+}
+impl QuestionStats {
+    // Of the people answering, the share whose whole group answered too
+    pub fn unanimous_percent(&self) -> f64 {
+        if self.people == 0 {
+            return 0.0;
+        }
+        100.0 * self.unanimous_people as f64 / self.people as f64
+    }
+}
+
+// Per-question counts over every group, in alphabet order
+pub struct Stats {
+    pub alphabet: Alphabet,
+    pub questions: Vec<QuestionStats>,
+    // People answering both questions; the diagonal is everyone answering
+    pub co_occurrence: Vec<Vec<usize>>,
+}
+impl Stats {
+    pub fn new(groups: &[Group], alphabet: &Alphabet) -> Stats {
+        let n = alphabet.questions().len();
+        let mut stats = Stats {
+            alphabet: alphabet.clone(),
+            questions: vec![QuestionStats::default(); n],
+            co_occurrence: vec![vec![0; n]; n],
+        };
+        for group in groups {
+            stats.add(group);
+        }
+        stats
+    }
+
+    fn add(&mut self, group: &Group) {
+        let (union, unanimous) = (group.union(), group.intersection());
+        for (i, stats) in self.questions.iter_mut().enumerate() {
+            stats.groups += union.contains(i) as usize;
+            if unanimous.contains(i) {
+                stats.unanimous_groups += 1;
+                stats.unanimous_people += group.people.len();
+            }
+        }
+        for person in &group.people {
+            for i in person.indexes() {
+                self.questions[i].people += 1;
+                for j in person.indexes() {
+                    self.co_occurrence[i][j] += 1;
+                }
+            }
+        }
+    }
+
+    pub fn export(&self, format: Format) -> String {
+        let questions = || self.alphabet.questions().iter();
+        let mut out = String::new();
+        match format {
+            Format::Csv => {
+                out.push_str("question,groups,people,unanimous_groups,unanimous_percent");
+                for question in questions() {
+                    out.push_str(&format!(",{}", csv_field(&format!("with_{}", question))));
+                }
+                out.push('\n');
+                for ((question, stats), row) in
+                    questions().zip(&self.questions).zip(&self.co_occurrence)
+                {
+                    out.push_str(&format!(
+                        "{},{},{},{},{:.1}",
+                        csv_field(&question.to_string()),
+                        stats.groups,
+                        stats.people,
+                        stats.unanimous_groups,
+                        stats.unanimous_percent()
+                    ));
+                    for count in row {
+                        out.push_str(&format!(",{}", count));
+                    }
+                    out.push('\n');
+                }
+            }
+            // Co-occurrences leave out the question itself and zero counts
+            Format::Json => {
+                out.push('[');
+                for (i, (question, stats)) in questions().zip(&self.questions).enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    let with: Vec<String> = questions()
+                        .zip(&self.co_occurrence[i])
+                        .enumerate()
+                        .filter(|&(j, (_, &count))| j != i && count > 0)
+                        .map(|(_, (other, count))| {
+                            format!("{}: {}", json_string(&other.to_string()), count)
+                        })
+                        .collect();
+                    out.push_str(&format!(
+                        "\n  {{\"question\": {}, \"groups\": {}, \"people\": {}, \"unanimous_groups\": {}, \"unanimous_percent\": {:.1}, \"with\": {{{}}}}}",
+                        json_string(&question.to_string()),
+                        stats.groups,
+                        stats.people,
+                        stats.unanimous_groups,
+                        stats.unanimous_percent(),
+                        with.join(", ")
+                    ));
+                }
+                out.push_str("\n]\n");
+            }
+        }
+        out
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(alphabet: &Alphabet, groups: &[&[&str]]) -> Vec<Group> {
+        groups
+            .iter()
+            .map(|people| Group {
+                questions: alphabet.all(),
+                people: people.iter().map(|p| alphabet.parse(p, 1).0).collect(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn question_stats() {
+        let alphabet = Alphabet::default();
+        let stats = Stats::new(
+            &groups(&alphabet, &[&["ab", "a"], &["b"], &["ac", "b", "c"]]),
+            &alphabet,
+        );
+        let a = &stats.questions[0];
+        assert_eq!(
+            *a,
+            QuestionStats {
+                groups: 2,
+                people: 3,
+                unanimous_groups: 1,
+                unanimous_people: 2
+            }
+        );
+        assert_eq!(format!("{:.1}", a.unanimous_percent()), "66.7");
+        assert_eq!(stats.questions[1].unanimous_groups, 1);
+        assert_eq!(stats.questions[25], QuestionStats::default());
+        assert_eq!(stats.questions[25].unanimous_percent(), 0.0);
+
+        assert_eq!(stats.co_occurrence[0][0], 3);
+        assert_eq!(stats.co_occurrence[0][1], 1);
+        assert_eq!(stats.co_occurrence[1][0], 1);
+        assert_eq!(stats.co_occurrence[0][2], 1);
+        assert_eq!(stats.co_occurrence[1][2], 0);
+    }
+
+    #[test]
+    fn exports() {
+        let alphabet = Alphabet::default();
+        let stats = Stats::new(&groups(&alphabet, &[&["ab", "a"]]), &alphabet);
+
+        let csv = stats.export(Format::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 27);
+        assert!(lines[0].starts_with(
+            "question,groups,people,unanimous_groups,unanimous_percent,with_a,with_b,with_c,"
+        ));
+        assert!(lines[1].starts_with("a,1,2,1,100.0,2,1,0,"));
+        assert!(lines[2].starts_with("b,1,1,0,0.0,1,1,0,"));
+
+        let json = stats.export(Format::Json);
+        assert_eq!(
+            json.lines().nth(1),
+            Some(
+                r#"  {"question": "a", "groups": 1, "people": 2, "unanimous_groups": 1, "unanimous_percent": 100.0, "with": {"b": 1}},"#
+            )
+        );
+        assert_eq!(json.lines().count(), 28);
+    }
+
+    #[test]
+    fn custom_alphabet_exports() {
+        let alphabet: Alphabet = "α,\"".parse().unwrap();
+        let stats = Stats::new(&groups(&alphabet, &[&["α,", "\""]]), &alphabet);
+        let csv = stats.export(Format::Csv);
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            vec![
+                "question,groups,people,unanimous_groups,unanimous_percent,with_α,\"with_,\",\"with_\"\"\"",
+                "α,1,1,0,0.0,1,1,0",
+                "\",\",1,1,0,0.0,1,1,0",
+                "\"\"\"\",1,1,0,0.0,0,0,1",
+            ]
+        );
+        assert!(stats
+            .export(Format::Json)
+            .contains(r#"{"question": "\"", "groups": 1"#));
+    }
+}
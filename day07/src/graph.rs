@@ -0,0 +1,280 @@
+use crate::{give_me, Child, Colour, Qty};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+#[derive(PartialEq, Debug)]
+pub enum GraphError {
+    // No rule says what this colour holds
+    UndefinedColour(Colour),
+    // Colours that end up inside themselves, first and last the same
+    Cycle(Vec<Colour>),
+}
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::UndefinedColour(colour) => write!(f, "no rule for {} bags", colour),
+            GraphError::Cycle(path) => write!(f, "bags inside themselves: {}", path.join(" → ")),
+        }
+    }
+}
+
+// Every bag rule, indexed both ways
+#[derive(Default, Debug)]
+pub struct BagGraph {
+    // Bags directly inside each bag. Bags that hold nothing map to an
+    // empty list, so every colour with a rule is a key.
+    contents: HashMap<Colour, Vec<Child>>,
+    // Bags directly holding each bag, with how many of it they hold
+    containers: HashMap<Colour, Vec<(Qty, Colour)>>,
+}
+
+impl BagGraph {
+    pub fn new() -> BagGraph {
+        BagGraph::default()
+    }
+
+    // Replaces any earlier rule for the colour
+    pub fn add(&mut self, colour: Colour, children: Vec<Child>) {
+        for (_, child) in self.contents.get(&colour).into_iter().flatten() {
+            if let Some(containers) = self.containers.get_mut(child) {
+                containers.retain(|(_, big)| *big != colour);
+            }
+        }
+        for (qty, child) in &children {
+            self.containers
+                .entry(child.clone())
+                .or_default()
+                .push((*qty, colour.clone()));
+        }
+        self.contents.insert(colour, children);
+    }
+
+    // Fails if a bag ends up inside itself
+    pub fn from_file(path: &str) -> Result<BagGraph, GraphError> {
+        let file = File::open(path).expect("problem reading file");
+        let reader = BufReader::new(file);
+
+        let mut graph = BagGraph::new();
+        for line in reader.lines().map(Result::unwrap) {
+            let (colour, children) = give_me(&line);
+            graph.add(colour, children);
+        }
+        match graph.find_cycle() {
+            Some(cycle) => Err(GraphError::Cycle(cycle)),
+            None => Ok(graph),
+        }
+    }
+
+    // The first cycle found, starting from colours in sorted order. Bags
+    // without a rule are treated as empty.
+    pub fn find_cycle(&self) -> Option<Vec<Colour>> {
+        let mut colours: Vec<&str> = self.contents.keys().map(String::as_str).collect();
+        colours.sort_unstable();
+
+        let mut done: HashSet<&str> = HashSet::new();
+        for colour in colours {
+            let mut path = vec![];
+            if let Some(cycle) = self.find_cycle_from(colour, &mut path, &mut done) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    fn find_cycle_from<'a>(
+        &'a self,
+        colour: &'a str,
+        path: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> Option<Vec<Colour>> {
+        if let Some(start) = path.iter().position(|&c| c == colour) {
+            let mut cycle: Vec<Colour> = path[start..].iter().map(|c| c.to_string()).collect();
+            cycle.push(colour.to_string());
+            return Some(cycle);
+        }
+        if done.contains(colour) {
+            return None;
+        }
+
+        path.push(colour);
+        for (_, child) in self.contents(colour) {
+            if let Some(cycle) = self.find_cycle_from(child, path, done) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        done.insert(colour);
+        None
+    }
+
+    // Forward adjacency: (qty, colour) for each bag directly inside
+    pub fn contents(&self, colour: &str) -> &[Child] {
+        self.contents.get(colour).map_or(&[], Vec::as_slice)
+    }
+
+    // Reverse adjacency: (qty, colour) for each bag directly holding this one
+    pub fn containers(&self, colour: &str) -> &[(Qty, Colour)] {
+        self.containers.get(colour).map_or(&[], Vec::as_slice)
+    }
+
+    fn check_defined(&self, colour: &str) -> Result<(), GraphError> {
+        if self.contents.contains_key(colour) {
+            Ok(())
+        } else {
+            Err(GraphError::UndefinedColour(colour.to_string()))
+        }
+    }
+
+    // How many bags one bag of this colour holds, all the way down
+    pub fn count_inside(&self, colour: &str) -> Result<usize, GraphError> {
+        self.count_inside_memo(colour, &mut HashMap::new(), &mut vec![])
+    }
+
+    // `table` holds None while a colour's count is being worked out, so
+    // meeting None again means a cycle
+    fn count_inside_memo<'a>(
+        &'a self,
+        colour: &'a str,
+        table: &mut HashMap<&'a str, Option<Qty>>,
+        path: &mut Vec<&'a str>,
+    ) -> Result<Qty, GraphError> {
+        match table.get(colour) {
+            Some(Some(count)) => return Ok(*count),
+            Some(None) => {
+                let start = path.iter().position(|&c| c == colour).unwrap();
+                let mut cycle: Vec<Colour> = path[start..].iter().map(|c| c.to_string()).collect();
+                cycle.push(colour.to_string());
+                return Err(GraphError::Cycle(cycle));
+            }
+            None => self.check_defined(colour)?,
+        }
+
+        table.insert(colour, None);
+        path.push(colour);
+        let mut count = 0;
+        for (qty, child) in self.contents(colour) {
+            count += qty + qty * self.count_inside_memo(child, table, path)?;
+        }
+        path.pop();
+        table.insert(colour, Some(count));
+        Ok(count)
+    }
+
+    // How many colours can hold this one, at any depth
+    pub fn count_containers(&self, colour: &str) -> Result<usize, GraphError> {
+        self.check_defined(colour)?;
+        let mut bags_to_find: Vec<&str> = vec![colour];
+        let mut bags_final: HashSet<&str> = HashSet::new();
+
+        while let Some(small) = bags_to_find.pop() {
+            for (_, big) in self.containers(small) {
+                if bags_final.insert(big) {
+                    bags_to_find.push(big);
+                }
+            }
+        }
+        Ok(bags_final.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_queries() {
+        let graph = BagGraph::from_file("./src/test.txt").unwrap();
+        assert_eq!(graph.count_containers("shiny gold"), Ok(4));
+        assert_eq!(graph.count_inside("shiny gold"), Ok(32));
+        assert_eq!(graph.count_inside("faded blue"), Ok(0));
+        assert_eq!(graph.count_containers("light red"), Ok(0));
+    }
+
+    #[test]
+    fn adjacency() {
+        let mut graph = BagGraph::new();
+        graph.add(
+            "c".into(),
+            vec![(1, "b".into()), (2, "a".into()), (3, "d".into())],
+        );
+        graph.add(
+            "f".into(),
+            vec![(4, "x".into()), (5, "a".into()), (6, "c".into())],
+        );
+
+        assert_eq!(
+            graph.contents("c"),
+            &[(1, "b".into()), (2, "a".into()), (3, "d".into())]
+        );
+        assert_eq!(graph.containers("a"), &[(2, "c".into()), (5, "f".into())]);
+        assert_eq!(graph.containers("c"), &[(6, "f".into())]);
+        assert!(graph.contents("a").is_empty());
+
+        graph.add("a".into(), vec![]);
+        graph.add("b".into(), vec![]);
+        assert_eq!(graph.count_containers("a"), Ok(2));
+        assert_eq!(graph.count_containers("b"), Ok(2));
+        assert_eq!(graph.count_containers("c"), Ok(1));
+    }
+
+    #[test]
+    fn nested_counts() {
+        let mut graph = BagGraph::new();
+        graph.add("violet".into(), vec![]);
+        graph.add("blue".into(), vec![(2, "violet".into())]);
+        graph.add("green".into(), vec![(2, "blue".into())]);
+        graph.add("yellow".into(), vec![(2, "green".into())]);
+        graph.add("orange".into(), vec![(2, "yellow".into())]);
+        graph.add("red".into(), vec![(2, "orange".into())]);
+        graph.add("gold".into(), vec![(2, "red".into())]);
+
+        assert_eq!(graph.count_inside("gold"), Ok(126));
+    }
+
+    #[test]
+    fn undefined_colours() {
+        let mut graph = BagGraph::new();
+        graph.add("red".into(), vec![(1, "blue".into())]);
+
+        let blue = GraphError::UndefinedColour("blue".into());
+        assert_eq!(graph.count_inside("red"), Err(blue));
+        assert_eq!(
+            graph.count_containers("green").unwrap_err().to_string(),
+            "no rule for green bags"
+        );
+        assert_eq!(graph.find_cycle(), None);
+    }
+
+    #[test]
+    fn cycles() {
+        let mut graph = BagGraph::new();
+        graph.add("red".into(), vec![(1, "blue".into())]);
+        graph.add("blue".into(), vec![(2, "green".into()), (1, "red".into())]);
+        graph.add("green".into(), vec![]);
+        graph.add("gold".into(), vec![(3, "blue".into())]);
+
+        let cycle = graph.find_cycle().unwrap();
+        assert_eq!(cycle, vec!["blue", "red", "blue"]);
+        assert_eq!(
+            GraphError::Cycle(cycle).to_string(),
+            "bags inside themselves: blue → red → blue"
+        );
+        assert_eq!(
+            graph.count_inside("gold"),
+            Err(GraphError::Cycle(vec![
+                "blue".into(),
+                "red".into(),
+                "blue".into()
+            ]))
+        );
+        assert_eq!(graph.count_inside("green"), Ok(0));
+        assert_eq!(graph.count_containers("green"), Ok(3));
+
+        graph.add("self".into(), vec![(1, "self".into())]);
+        graph.add("blue".into(), vec![]);
+        assert!(graph.containers("red").is_empty());
+        assert_eq!(graph.find_cycle(), Some(vec!["self".into(), "self".into()]));
+    }
+}